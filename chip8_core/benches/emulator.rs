@@ -0,0 +1,66 @@
+// Benchmarks of the instruction dispatch and the drawing paths, see README.md.
+//
+// The ROM benchmarks use the games bundled in web/roms, a ROM that is missing is skipped.
+
+use chip8_core::{Emu, FONTSET_START_ADDR};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::Path;
+
+const ROMS: &[&str] = &["BRIX", "PONG", "INVADERS"];
+const TICKS: usize = 100_000;
+
+fn emu_with(program: &[u8]) -> Emu
+{
+    let mut emu = Emu::with_seed(0);
+    emu.load(program);
+    emu
+}
+
+// 100k instructions of a real game, with a frame every 10 like the frontends run them
+// A new emulator every iteration, so every run starts from the same state
+fn roms(c: &mut Criterion)
+{
+    let roms_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../web/roms");
+
+    for name in ROMS {
+        let Ok(rom) = fs::read(roms_dir.join(name)) else {
+            eprintln!("Skipping {}, it isn't in {}", name, roms_dir.display());
+            continue;
+        };
+
+        c.bench_function(&format!("{} 100k ticks", name), |b| {
+            b.iter(|| {
+                let mut emu = emu_with(&rom);
+                for _ in 0..TICKS / 10 {
+                    emu.run_frame(10).unwrap();
+                }
+                emu.pc()
+            })
+        });
+    }
+}
+
+// The micro benchmarks loop over the opcode, so each iteration also runs the JP back to it
+fn draw(c: &mut Criterion)
+{
+    // LD V0, 30; LD V1, 10; LD I, font; DRW V0, V1, 5; JP 0x206
+    let [hi, lo] = (0xA000 | FONTSET_START_ADDR).to_be_bytes();
+    let program = [0x60, 30, 0x61, 10, hi, lo, 0xD0, 0x15, 0x12, 0x06];
+    let mut emu = emu_with(&program);
+    emu.run_cycles(3);
+
+    c.bench_function("DRW 8x5", |b| b.iter(|| emu.run_cycles(2)));
+}
+
+fn clear_screen(c: &mut Criterion)
+{
+    // CLS; JP 0x200
+    let program = [0x00, 0xE0, 0x12, 0x00];
+    let mut emu = emu_with(&program);
+
+    c.bench_function("CLS", |b| b.iter(|| emu.run_cycles(2)));
+}
+
+criterion_group!(benches, roms, draw, clear_screen);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use crate::{Emu, NUM_PLANES, SCREEN_WORDS};
+
+impl Emu {
+    // the planes frontends are shown: the composited frame with the `accumulate_draws` quirk,
+    // the screen itself otherwise
+    pub(crate) fn display_planes(&self) -> &[[u64; SCREEN_WORDS]; NUM_PLANES]
+    {
+        if self.quirks.accumulate_draws {
+            &self.visible_screen
+        } else {
+            &self.screen
+        }
+    }
+
+    // remember the pixels of row y a DRAW or a scroll just wrote, so a pixel that is only
+    // lit in the middle of a frame still shows up in it
+    pub(crate) fn accumulate_row(&mut self, plane: usize, y: usize)
+    {
+        let words = if self.hires { 2 * y..2 * y + 2 } else { y..y + 1 };
+
+        for idx in words {
+            self.drawn_screen[plane][idx] |= self.screen[plane][idx];
+        }
+    }
+
+    // once per frame: show every pixel lit during the frame, and start the next one from
+    // what is on the screen now, so pixels are only cleared at frame boundaries
+    pub(crate) fn composite_frame(&mut self)
+    {
+        if self.visible_screen != self.drawn_screen {
+            self.visible_screen = self.drawn_screen;
+            self.screen_dirty = true;
+        }
+        self.drawn_screen = self.screen;
+    }
+
+    // show the screen as it is, e.g. after a reset or a resolution switch
+    pub(crate) fn sync_visible_screen(&mut self)
+    {
+        self.visible_screen = self.screen;
+        self.drawn_screen = self.screen;
+    }
+}
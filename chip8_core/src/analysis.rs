@@ -0,0 +1,84 @@
+use crate::disasm::disassemble;
+use crate::{decode, Emu, Instruction, BIG_FONTSET_SIZE, BIG_FONTSET_START_ADDR, FONTSET_SIZE, FONTSET_START_ADDR};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+impl Emu {
+    /// Lists the loaded ROM from the start address to its end, one instruction per line with
+    /// its address and raw bytes, e.g. `0x200: 6A02    LD VA, 0x02`.
+    ///
+    /// Like `opcode_coverage` this is a static scan, so it can't tell code from data for sure.
+    /// Words that don't decode to an instruction are taken to be data and listed as
+    /// `DB 0xNN, 0xNN`, which is how sprites usually show up. The listing starts with
+    /// comments on where the fonts live, below the ROM.
+    pub fn listing(&self) -> String
+    {
+        let mut listing = String::new();
+        let _ = writeln!(listing, "; 0x{:03X}: font, {} bytes", FONTSET_START_ADDR, FONTSET_SIZE);
+        let _ = writeln!(listing, "; 0x{:03X}: big font, {} bytes", BIG_FONTSET_START_ADDR, BIG_FONTSET_SIZE);
+
+        // The start address may have moved since the ROM was loaded
+        let start = self.start_addr as usize;
+        let rom = &self.ram[start..(start + self.rom_len).min(self.ram.len())];
+
+        let mut offset = 0;
+        while offset < rom.len() {
+            let addr = start + offset;
+            let bytes = &rom[offset..rom.len().min(offset + 2)];
+
+            let (len, text) = match *bytes {
+                // The long load carries its address in the following 2 bytes
+                [0xF0, 0x00] if offset + 4 <= rom.len() => {
+                    let long_addr = (rom[offset + 2] as u16) << 8 | rom[offset + 3] as u16;
+                    (4, format!("LD I, 0x{:04X}", long_addr))
+                }
+                [hi, lo] => match decode((hi as u16) << 8 | lo as u16) {
+                    Instruction::Unknown(_) => (2, format!("DB 0x{:02X}, 0x{:02X}", hi, lo)),
+                    _ => (2, disassemble((hi as u16) << 8 | lo as u16)),
+                },
+                // A ROM of odd length ends on a lone byte
+                _ => (1, format!("DB 0x{:02X}", bytes[0])),
+            };
+
+            let raw = rom[offset..offset + len]
+                .chunks(2)
+                .map(|word| word.iter().map(|byte| format!("{:02X}", byte)).collect::<String>())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(listing, "0x{:03X}: {:<7} {}", addr, raw, text);
+
+            offset += len;
+        }
+
+        listing
+    }
+
+    /// Counts the instructions of the loaded ROM by mnemonic, see `Instruction::mnemonic`.
+    ///
+    /// This is a static scan: every 2 bytes from the start address to the end of the ROM are decoded
+    /// without running anything, so sprite data is counted too and `F000 NNNN` is treated as
+    /// a single 4 byte instruction. It's an approximation, but enough to tell which
+    /// extensions a ROM is likely to use.
+    pub fn opcode_coverage(&self) -> BTreeMap<&'static str, usize>
+    {
+        let mut counts = BTreeMap::new();
+
+        let start = self.start_addr as usize;
+        let rom = &self.ram[start..start + self.rom_len];
+
+        let mut addr = 0;
+        while addr + 1 < rom.len() {
+            let op = (rom[addr] as u16) << 8 | rom[addr + 1] as u16;
+            let instruction = decode(op);
+            *counts.entry(instruction.mnemonic()).or_insert(0) += 1;
+
+            // The long load carries its address in the following 2 bytes
+            addr += if instruction == Instruction::LoadLongIndex { 4 } else { 2 };
+        }
+
+        counts
+    }
+}
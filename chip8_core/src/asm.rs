@@ -0,0 +1,305 @@
+use crate::START_ADDR;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// AsmError is an error raised by `assemble`, every variant carries the 1-based source line.
+///
+/// It contains the following variants:
+/// - `UnknownMnemonic`: The instruction isn't one this assembler knows
+/// - `BadOperands`: The operands don't match any form of the instruction
+/// - `BadValue`: A number is malformed or too large for where it's used
+/// - `UnknownLabel`: An operand refers to a label that is never defined
+/// - `DuplicateLabel`: The same label is defined twice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    BadOperands { line: usize },
+    BadValue { line: usize, value: String },
+    UnknownLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::UnknownMnemonic { line, mnemonic } => write!(f, "line {}: unknown mnemonic {}", line, mnemonic),
+            AsmError::BadOperands { line } => write!(f, "line {}: invalid operands", line),
+            AsmError::BadValue { line, value } => write!(f, "line {}: invalid value {}", line, value),
+            AsmError::UnknownLabel { line, label } => write!(f, "line {}: unknown label {}", line, label),
+            AsmError::DuplicateLabel { line, label } => write!(f, "line {}: label {} is already defined", line, label),
+        }
+    }
+}
+
+impl core::error::Error for AsmError {}
+
+// An operand as written, numbers and labels are resolved in the second pass
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    Reg(u8),
+    I,
+    IndirectI,
+    Delay,
+    Sound,
+    Key,
+    Font,
+    BigFont,
+    Bcd,
+    Flags,
+    Number(u32),
+    Label(String),
+}
+
+// One instruction or data directive, with the line it came from
+struct Statement {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<Operand>,
+}
+
+/// Assembles CHIP-8 source into a ROM meant to be loaded at 0x200.
+///
+/// The syntax follows Cowgod's reference and the output of `disasm::disassemble`:
+/// one instruction per line, mnemonics and registers are case-insensitive, and
+/// everything after a `;` is a comment. Numbers are decimal, `0x` hex or `0b` binary.
+///
+/// - `name:` defines a label at the current address, it can be used anywhere an
+///   address or a byte is expected, e.g. `JP loop` or `LD I, sprite`
+/// - `DB 0x3C, 0x42` emits raw bytes and `DW 0x1234` emits big-endian words
+/// - `LD I, NNNN` with a value over 0xFFF emits the XO-CHIP `F000 NNNN` long load
+///
+/// # Errors
+///
+/// Returns the first `AsmError` found, see its variants.
+pub fn assemble(src: &str) -> Result<Vec<u8>, AsmError>
+{
+    // First pass: parse every line and give each label the address it lands at
+    let mut statements = Vec::new();
+    let mut labels = BTreeMap::new();
+    let mut addr = START_ADDR as usize;
+
+    for (idx, text) in src.lines().enumerate() {
+        let line = idx + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+
+        while let Some((label, rest)) = split_label(text) {
+            if labels.insert(label.to_string(), addr).is_some() {
+                return Err(AsmError::DuplicateLabel { line, label: label.to_string() });
+            }
+            text = rest;
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line, text)?;
+        addr += statement_size(&statement);
+        statements.push(statement);
+    }
+
+    // Second pass: encode, now that every label is known
+    let mut rom = Vec::new();
+    for statement in &statements {
+        encode(statement, &labels, &mut rom)?;
+    }
+
+    Ok(rom)
+}
+
+// A leading `name:`, split from the rest of the line
+fn split_label(text: &str) -> Option<(&str, &str)>
+{
+    let (label, rest) = text.split_once(':')?;
+    let label = label.trim();
+
+    is_label_name(label).then(|| (label, rest.trim()))
+}
+
+// Labels are identifiers: a letter or underscore, then letters, digits or underscores
+fn is_label_name(text: &str) -> bool
+{
+    text.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_statement(line: usize, text: &str) -> Result<Statement, AsmError>
+{
+    let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+    let operands = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',')
+            .map(|operand| parse_operand(line, operand.trim()))
+            .collect::<Result<_, _>>()?
+    };
+
+    Ok(Statement { line, mnemonic: mnemonic.to_ascii_uppercase(), operands })
+}
+
+fn parse_operand(line: usize, text: &str) -> Result<Operand, AsmError>
+{
+    let upper = text.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Delay,
+        "ST" => Operand::Sound,
+        "K" => Operand::Key,
+        "F" => Operand::Font,
+        "HF" => Operand::BigFont,
+        "B" => Operand::Bcd,
+        "R" => Operand::Flags,
+        _ if upper.len() == 2 && upper.starts_with('V') => {
+            let reg = u8::from_str_radix(&upper[1..], 16)
+                .map_err(|_| AsmError::BadValue { line, value: text.to_string() })?;
+            Operand::Reg(reg)
+        },
+        _ if upper.starts_with(|c: char| c.is_ascii_digit()) => {
+            let parsed = if let Some(hex) = upper.strip_prefix("0X") {
+                u32::from_str_radix(hex, 16)
+            } else if let Some(bin) = upper.strip_prefix("0B") {
+                u32::from_str_radix(bin, 2)
+            } else {
+                upper.parse()
+            };
+            Operand::Number(parsed.map_err(|_| AsmError::BadValue { line, value: text.to_string() })?)
+        },
+        _ if is_label_name(text) => Operand::Label(text.to_string()),
+        _ => return Err(AsmError::BadValue { line, value: text.to_string() }),
+    };
+
+    Ok(operand)
+}
+
+// Bytes taken by a statement, needed to place the labels before anything is encoded
+fn statement_size(statement: &Statement) -> usize
+{
+    match (statement.mnemonic.as_str(), statement.operands.as_slice()) {
+        ("DB", operands) => operands.len(),
+        ("DW", operands) => operands.len() * 2,
+        ("LD", [Operand::I, Operand::Number(nnnn)]) if *nnnn > 0xFFF => 4,
+        _ => 2,
+    }
+}
+
+fn encode(statement: &Statement, labels: &BTreeMap<String, usize>, rom: &mut Vec<u8>) -> Result<(), AsmError>
+{
+    use Operand::*;
+
+    let line = statement.line;
+
+    // Resolve a number or a label, failing if it doesn't fit in `max`
+    let value = |operand: &Operand, max: u32| -> Result<u16, AsmError> {
+        let (value, text) = match operand {
+            Number(value) => (*value, value.to_string()),
+            Label(label) => {
+                let addr = labels.get(label).ok_or_else(|| AsmError::UnknownLabel { line, label: label.clone() })?;
+                (*addr as u32, label.clone())
+            },
+            _ => return Err(AsmError::BadOperands { line }),
+        };
+
+        if value > max {
+            return Err(AsmError::BadValue { line, value: text });
+        }
+
+        Ok(value as u16)
+    };
+    let addr = |operand: &Operand| value(operand, 0xFFF);
+    let byte = |operand: &Operand| value(operand, 0xFF);
+    let nibble = |operand: &Operand| value(operand, 0xF);
+
+    // Place the register indices in the X and Y digits
+    let xy = |x: u8, y: u8| ((x as u16) << 8) | ((y as u16) << 4);
+
+    let op = match (statement.mnemonic.as_str(), statement.operands.as_slice()) {
+        ("DB", operands) => {
+            for operand in operands {
+                rom.push(byte(operand)? as u8);
+            }
+            return Ok(());
+        },
+        ("DW", operands) => {
+            for operand in operands {
+                rom.extend_from_slice(&value(operand, 0xFFFF)?.to_be_bytes());
+            }
+            return Ok(());
+        },
+        ("LD", [I, Number(nnnn)]) if *nnnn > 0xFFF => {
+            rom.extend_from_slice(&0xF000u16.to_be_bytes());
+            rom.extend_from_slice(&value(&Number(*nnnn), 0xFFFF)?.to_be_bytes());
+            return Ok(());
+        },
+
+        ("NOP", []) => 0x0000,
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SCD", [n]) => 0x00C0 | nibble(n)?,
+        ("SCR", []) => 0x00FB,
+        ("SCL", []) => 0x00FC,
+        ("EXIT", []) => 0x00FD,
+        ("LOW", []) => 0x00FE,
+        ("HIGH", []) => 0x00FF,
+        ("JP", [Reg(0), nnn]) => 0xB000 | addr(nnn)?,
+        ("JP", [nnn]) => 0x1000 | addr(nnn)?,
+        ("CALL", [nnn]) => 0x2000 | addr(nnn)?,
+        ("SE", [Reg(x), Reg(y)]) => 0x5000 | xy(*x, *y),
+        ("SE", [Reg(x), nn]) => 0x3000 | xy(*x, 0) | byte(nn)?,
+        ("SNE", [Reg(x), Reg(y)]) => 0x9000 | xy(*x, *y),
+        ("SNE", [Reg(x), nn]) => 0x4000 | xy(*x, 0) | byte(nn)?,
+        ("LD", [Reg(x), Reg(y)]) => 0x8000 | xy(*x, *y),
+        ("LD", [Reg(x), Delay]) => 0xF007 | xy(*x, 0),
+        ("LD", [Reg(x), Key]) => 0xF00A | xy(*x, 0),
+        ("LD", [Reg(x), IndirectI]) => 0xF065 | xy(*x, 0),
+        ("LD", [Reg(x), Flags]) => 0xF085 | xy(*x, 0),
+        ("LD", [Reg(x), nn]) => 0x6000 | xy(*x, 0) | byte(nn)?,
+        ("LD", [I, nnn]) => 0xA000 | addr(nnn)?,
+        ("LD", [Delay, Reg(x)]) => 0xF015 | xy(*x, 0),
+        ("LD", [Sound, Reg(x)]) => 0xF018 | xy(*x, 0),
+        ("LD", [Font, Reg(x)]) => 0xF029 | xy(*x, 0),
+        ("LD", [BigFont, Reg(x)]) => 0xF030 | xy(*x, 0),
+        ("LD", [Bcd, Reg(x)]) => 0xF033 | xy(*x, 0),
+        ("LD", [IndirectI, Reg(x)]) => 0xF055 | xy(*x, 0),
+        ("LD", [Flags, Reg(x)]) => 0xF075 | xy(*x, 0),
+        ("ADD", [Reg(x), Reg(y)]) => 0x8004 | xy(*x, *y),
+        ("ADD", [I, Reg(x)]) => 0xF01E | xy(*x, 0),
+        ("ADD", [Reg(x), nn]) => 0x7000 | xy(*x, 0) | byte(nn)?,
+        ("OR", [Reg(x), Reg(y)]) => 0x8001 | xy(*x, *y),
+        ("AND", [Reg(x), Reg(y)]) => 0x8002 | xy(*x, *y),
+        ("XOR", [Reg(x), Reg(y)]) => 0x8003 | xy(*x, *y),
+        ("SUB", [Reg(x), Reg(y)]) => 0x8005 | xy(*x, *y),
+        ("SHR", [Reg(x)]) => 0x8006 | xy(*x, 0),
+        ("SHR", [Reg(x), Reg(y)]) => 0x8006 | xy(*x, *y),
+        ("SUBN", [Reg(x), Reg(y)]) => 0x8007 | xy(*x, *y),
+        ("SHL", [Reg(x)]) => 0x800E | xy(*x, 0),
+        ("SHL", [Reg(x), Reg(y)]) => 0x800E | xy(*x, *y),
+        ("RND", [Reg(x), nn]) => 0xC000 | xy(*x, 0) | byte(nn)?,
+        ("DRW", [Reg(x), Reg(y), n]) => 0xD000 | xy(*x, *y) | nibble(n)?,
+        ("SKP", [Reg(x)]) => 0xE09E | xy(*x, 0),
+        ("SKNP", [Reg(x)]) => 0xE0A1 | xy(*x, 0),
+        ("PLANE", [n]) => 0xF001 | (nibble(n)? << 8),
+        ("AUDIO", []) => 0xF002,
+        ("PITCH", [Reg(x)]) => 0xF03A | xy(*x, 0),
+
+        (mnemonic, _) if is_mnemonic(mnemonic) => return Err(AsmError::BadOperands { line }),
+        (mnemonic, _) => return Err(AsmError::UnknownMnemonic { line, mnemonic: mnemonic.to_string() }),
+    };
+
+    rom.extend_from_slice(&op.to_be_bytes());
+    Ok(())
+}
+
+// Tells operand mistakes apart from typos in the mnemonic
+fn is_mnemonic(mnemonic: &str) -> bool
+{
+    matches!(
+        mnemonic,
+        "NOP" | "CLS" | "RET" | "SCD" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "JP" | "CALL" | "SE" | "SNE"
+            | "LD" | "ADD" | "OR" | "AND" | "XOR" | "SUB" | "SHR" | "SUBN" | "SHL" | "RND" | "DRW"
+            | "SKP" | "SKNP" | "PLANE" | "AUDIO" | "PITCH"
+    )
+}
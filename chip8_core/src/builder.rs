@@ -0,0 +1,101 @@
+use crate::{Chip8Error, Emu, Quirks, StackOverflowPolicy};
+
+/// EmuBuilder configures an `Emu` in one place before it starts running.
+///
+/// `EmuBuilder::default().build()` gives the same emulator as `Emu::new()`.
+#[derive(Debug, Clone, Default)]
+pub struct EmuBuilder {
+    quirks: Quirks,
+    seed: Option<u64>,
+    hires: bool,
+    large_memory: bool,
+    target_ips: Option<u32>,
+    start_address: Option<u16>,
+    stack_size: Option<usize>,
+    stack_overflow_policy: StackOverflowPolicy,
+}
+
+impl EmuBuilder {
+    /// Selects the interpreter behaviors to emulate.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Seeds the random number generator, see `Emu::with_seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Starts in the SUPER-CHIP 128x64 resolution instead of 64x32.
+    pub fn hires(mut self, hires: bool) -> Self {
+        self.hires = hires;
+        self
+    }
+
+    /// Enables the XO-CHIP 64KB address space, see `Emu::set_large_memory`.
+    pub fn large_memory(mut self, large_memory: bool) -> Self {
+        self.large_memory = large_memory;
+        self
+    }
+
+    /// Sets the CPU speed in instructions per second, see `Emu::set_target_ips`.
+    pub fn target_ips(mut self, ips: u32) -> Self {
+        self.target_ips = Some(ips);
+        self
+    }
+
+    /// Loads ROMs and starts PC at `addr` instead of 0x200, see `Emu::set_start_address`.
+    ///
+    /// `build` fails if `addr` isn't within the 4KB address space.
+    pub fn start_address(mut self, addr: u16) -> Self {
+        self.start_address = Some(addr);
+        self
+    }
+
+    /// Sets how many return addresses the stack holds, see `Emu::set_stack_size`.
+    pub fn stack_size(mut self, size: usize) -> Self {
+        self.stack_size = Some(size);
+        self
+    }
+
+    /// Selects what a CALL does when the stack is full, see `StackOverflowPolicy`.
+    pub fn stack_overflow_policy(mut self, policy: StackOverflowPolicy) -> Self {
+        self.stack_overflow_policy = policy;
+        self
+    }
+
+    /// Creates the configured emulator.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::AddressOutOfRange` if the start address isn't within the 4KB
+    /// address space, like `Emu::set_start_address`.
+    pub fn build(self) -> Result<Emu, Chip8Error> {
+        let mut emu = Emu::new();
+
+        emu.set_quirks(self.quirks);
+        emu.set_large_memory(self.large_memory);
+        emu.hires = self.hires;
+        emu.set_stack_overflow_policy(self.stack_overflow_policy);
+
+        if let Some(size) = self.stack_size {
+            emu.set_stack_size(size);
+        }
+
+        if let Some(ips) = self.target_ips {
+            emu.set_target_ips(ips);
+        }
+
+        if let Some(addr) = self.start_address {
+            emu.set_start_address(addr)?;
+        }
+
+        if let Some(seed) = self.seed {
+            emu.reseed(seed);
+        }
+
+        Ok(emu)
+    }
+}
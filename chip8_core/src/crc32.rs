@@ -0,0 +1,15 @@
+// CRC-32 as used by zip and gzip (reflected polynomial 0xEDB88320),
+// computed bit by bit since ROMs are only a few KB
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,141 @@
+use crate::{decode, Chip8Error, Emu, Instruction, StackOverflowPolicy, TraceHook};
+
+/// StopReason explains why a debugging run returned control to the caller.
+///
+/// It contains the following variants:
+/// - `Breakpoint`: PC reached the breakpoint at the given address, which hasn't been executed yet
+/// - `Exhausted`: The cycle budget ran out before any breakpoint was reached
+/// - `Halted`: The program executed 00FD (EXIT), see `Emu::halted`
+/// - `Stepped`: `step_over` ran the instruction, and the whole subroutine if it was a CALL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(u16),
+    Exhausted,
+    Halted,
+    Stepped,
+}
+
+impl Emu {
+    /// Stops `tick_until_breakpoint` right before the instruction at `addr` is executed.
+    pub fn add_breakpoint(&mut self, addr: u16)
+    {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Removes a breakpoint added by `add_breakpoint`, returning whether it existed.
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool
+    {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Installs a hook that `tick` calls with the address and opcode of every fetched instruction.
+    ///
+    /// The hook runs right after the fetch, before the instruction is executed.
+    /// It replaces any hook installed before.
+    pub fn set_trace_hook(&mut self, hook: TraceHook)
+    {
+        self.trace_hook = Some(hook);
+    }
+
+    /// Removes the hook installed by `set_trace_hook`.
+    pub fn clear_trace_hook(&mut self)
+    {
+        self.trace_hook = None;
+    }
+
+    /// Runs up to `max_cycles` instructions, stopping when PC lands on a breakpoint.
+    ///
+    /// Breakpoints are checked before the fetch, so the instruction at the breakpoint
+    /// isn't executed. The instruction at the current PC always runs, which means
+    /// calling this again resumes past the breakpoint it stopped on.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error raised by an instruction, see `try_tick`.
+    pub fn tick_until_breakpoint(&mut self, max_cycles: usize) -> Result<StopReason, Chip8Error>
+    {
+        for cycle in 0..max_cycles {
+            if cycle > 0 && self.breakpoints.contains(&self.pc) {
+                return Ok(StopReason::Breakpoint(self.pc));
+            }
+
+            self.try_tick()?;
+
+            if self.halted {
+                return Ok(StopReason::Halted);
+            }
+        }
+
+        // The budget may run out just as a breakpoint is reached
+        if max_cycles > 0 && self.breakpoints.contains(&self.pc) {
+            return Ok(StopReason::Breakpoint(self.pc));
+        }
+
+        Ok(StopReason::Exhausted)
+    }
+
+    /// Runs the instruction at PC, and if it was a CALL keeps running until the subroutine returns,
+    /// so PC lands on the instruction after the call.
+    ///
+    /// The subroutine has returned once every CALL made since the step started has been
+    /// matched by a RET, and one more RET also ends the step. SP alone can't tell, it wraps
+    /// around with `StackOverflowPolicy::Wrap`. Breakpoints inside the subroutine stop it like
+    /// in `tick_until_breakpoint`, and at most `max_cycles` instructions run.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error raised by an instruction, see `try_tick`.
+    pub fn step_over(&mut self, max_cycles: usize) -> Result<StopReason, Chip8Error>
+    {
+        // CALLs entered minus RETs executed since the step started
+        let mut depth = 0;
+
+        for cycle in 0..max_cycles {
+            if cycle > 0 {
+                if depth <= 0 {
+                    return Ok(StopReason::Stepped);
+                }
+                if self.breakpoints.contains(&self.pc) {
+                    return Ok(StopReason::Breakpoint(self.pc));
+                }
+            }
+
+            depth += self.tick_call_depth()?;
+
+            if self.halted {
+                return Ok(StopReason::Halted);
+            }
+        }
+
+        if max_cycles > 0 && depth <= 0 {
+            return Ok(StopReason::Stepped);
+        }
+
+        Ok(StopReason::Exhausted)
+    }
+
+    // run one tick and return how it changed the call depth: 1 for a CALL that pushed its
+    // return address, -1 for a RET, 0 for anything else and for ticks that wait
+    fn tick_call_depth(&mut self) -> Result<i64, Chip8Error>
+    {
+        let higher_byte = self.ram[self.mask_addr(self.pc) as usize] as u16;
+        let lower_byte = self.ram[self.mask_addr(self.pc.wrapping_add(1)) as usize] as u16;
+        let cycles = self.cycles;
+        // The Ignore policy skips a CALL on a full stack
+        let call_dropped = self.sp as usize >= self.stack_size
+            && self.stack_overflow_policy == StackOverflowPolicy::Ignore;
+
+        self.try_tick()?;
+
+        if self.cycles == cycles {
+            return Ok(0);
+        }
+
+        let depth = match decode((higher_byte << 8) | lower_byte) {
+            Instruction::Call(_) if !call_dropped => 1,
+            Instruction::Return => -1,
+            _ => 0,
+        };
+        Ok(depth)
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String, vec::Vec};
+
+use crate::Emu;
+#[cfg(feature = "alloc")]
+use crate::NUM_PLANES;
+
+/// Two emulators are equal when their machine state is: registers, stack, timers, keys,
+/// memory, display, modes, settings and counters.
+///
+/// The random number generator and the debugging aids (history, breakpoints, hooks) are
+/// left out, so a fresh emulator equals another fresh one.
+impl PartialEq for Emu {
+    fn eq(&self, other: &Self) -> bool {
+        self.pc == other.pc
+            && self.i_reg == other.i_reg
+            && self.sp == other.sp
+            && self.dt == other.dt
+            && self.st == other.st
+            && self.v_reg == other.v_reg
+            && self.stack == other.stack
+            && self.stack_size == other.stack_size
+            && self.keys == other.keys
+            && self.waiting_for_key == other.waiting_for_key
+            && self.held_key == other.held_key
+            && self.hires == other.hires
+            && self.flags == other.flags
+            && self.plane_mask == other.plane_mask
+            && self.large_memory == other.large_memory
+            && self.audio_buffer == other.audio_buffer
+            && self.audio_pitch == other.audio_pitch
+            && self.quirks == other.quirks
+            && self.stack_overflow_policy == other.stack_overflow_policy
+            && self.target_ips == other.target_ips
+            && self.start_addr == other.start_addr
+            && self.waiting_for_vblank == other.waiting_for_vblank
+            && self.halted == other.halted
+            && self.rom_len == other.rom_len
+            && self.rom_crc == other.rom_crc
+            && self.cycles == other.cycles
+            && self.frames == other.frames
+            && self.screen == other.screen
+            && self.ram[..] == other.ram[..]
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Emu {
+    /// Lists every difference between the machine states of `self` and `other`,
+    /// one readable line each, e.g. `V3: 0x01 != 0x02` or `RAM 0x300..=0x303 differs`.
+    ///
+    /// It compares the same state as `==`, so the list is empty exactly when they are equal.
+    pub fn diff(&self, other: &Emu) -> Vec<String>
+    {
+        let mut diffs = Vec::new();
+
+        // Report a single value that differs
+        macro_rules! field {
+            ($name:expr, $field:ident) => {
+                if self.$field != other.$field {
+                    diffs.push(format!("{}: {:?} != {:?}", $name, self.$field, other.$field));
+                }
+            };
+        }
+
+        if self.pc != other.pc {
+            diffs.push(format!("PC: 0x{:03X} != 0x{:03X}", self.pc, other.pc));
+        }
+        if self.i_reg != other.i_reg {
+            diffs.push(format!("I: 0x{:03X} != 0x{:03X}", self.i_reg, other.i_reg));
+        }
+        field!("SP", sp);
+        field!("DT", dt);
+        field!("ST", st);
+
+        for (idx, (a, b)) in self.v_reg.iter().zip(&other.v_reg).enumerate() {
+            if a != b {
+                diffs.push(format!("V{:X}: 0x{:02X} != 0x{:02X}", idx, a, b));
+            }
+        }
+        for (idx, (a, b)) in self.stack.iter().zip(&other.stack).enumerate() {
+            if a != b {
+                diffs.push(format!("stack[{}]: 0x{:03X} != 0x{:03X}", idx, a, b));
+            }
+        }
+        for (idx, (a, b)) in self.keys.iter().zip(&other.keys).enumerate() {
+            if a != b {
+                diffs.push(format!("key {:X}: {} != {}", idx, a, b));
+            }
+        }
+
+        field!("waiting for key", waiting_for_key);
+        field!("held key", held_key);
+        field!("hires", hires);
+        field!("flags", flags);
+        field!("plane mask", plane_mask);
+        field!("large memory", large_memory);
+        field!("audio buffer", audio_buffer);
+        field!("audio pitch", audio_pitch);
+        field!("quirks", quirks);
+        field!("stack size", stack_size);
+        field!("stack overflow policy", stack_overflow_policy);
+        field!("target IPS", target_ips);
+        if self.start_addr != other.start_addr {
+            diffs.push(format!("start address: 0x{:03X} != 0x{:03X}", self.start_addr, other.start_addr));
+        }
+        field!("waiting for vblank", waiting_for_vblank);
+        field!("halted", halted);
+        field!("ROM size", rom_len);
+        field!("ROM CRC", rom_crc);
+        field!("cycles", cycles);
+        field!("frames", frames);
+
+        // Name the screen words that differ rather than every pixel
+        for plane in 0..NUM_PLANES {
+            let words = self.screen[plane]
+                .iter()
+                .zip(&other.screen[plane])
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(|(word, _)| word);
+            for (start, end) in runs(words) {
+                diffs.push(format!("screen plane {} words {}..={} differ", plane, start, end));
+            }
+        }
+
+        // Group changed bytes into address ranges
+        let bytes = self.ram
+            .iter()
+            .zip(&other.ram[..])
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(addr, _)| addr);
+        for (start, end) in runs(bytes) {
+            diffs.push(format!("RAM 0x{:03X}..=0x{:03X} differs", start, end));
+        }
+
+        diffs
+    }
+}
+
+// group increasing indices into inclusive (start, end) runs of consecutive values
+#[cfg(feature = "alloc")]
+fn runs(indices: impl Iterator<Item = usize>) -> Vec<(usize, usize)>
+{
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+
+    for idx in indices {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == idx => *end = idx,
+            _ => runs.push((idx, idx)),
+        }
+    }
+
+    runs
+}
@@ -0,0 +1,101 @@
+use crate::{decode, Instruction};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Turns a single opcode into a human-readable mnemonic, e.g. `LD V3, 0x2A`.
+///
+/// Registers are written as `V0`..`VF`, addresses as `0xNNN` and bytes as `0xNN`.
+/// Opcodes the emulator doesn't implement are rendered as `DW 0xNNNN`.
+///
+/// `F000` is followed by a 16 bit address which isn't part of the opcode, so it is
+/// rendered as `LD I, LONG` here. Use `disassemble_range` to include the address.
+pub fn disassemble(op: u16) -> String
+{
+    match decode(op) {
+        Instruction::Nop => "NOP".to_string(),
+        Instruction::ClearScreen => "CLS".to_string(),
+        Instruction::Return => "RET".to_string(),
+        Instruction::ScrollDown(n) => format!("SCD {}", n),
+        Instruction::ScrollRight => "SCR".to_string(),
+        Instruction::ScrollLeft => "SCL".to_string(),
+        Instruction::Exit => "EXIT".to_string(),
+        Instruction::LowRes => "LOW".to_string(),
+        Instruction::HighRes => "HIGH".to_string(),
+        Instruction::Jump(nnn) => format!("JP 0x{:03X}", nnn),
+        Instruction::Call(nnn) => format!("CALL 0x{:03X}", nnn),
+        Instruction::SkipEqImm { x, nn } => format!("SE V{:X}, 0x{:02X}", x, nn),
+        Instruction::SkipNeImm { x, nn } => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        Instruction::SkipEqReg { x, y } => format!("SE V{:X}, V{:X}", x, y),
+        Instruction::SetReg { x, nn } => format!("LD V{:X}, 0x{:02X}", x, nn),
+        Instruction::AddImm { x, nn } => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        Instruction::Move { x, y } => format!("LD V{:X}, V{:X}", x, y),
+        Instruction::Or { x, y } => format!("OR V{:X}, V{:X}", x, y),
+        Instruction::And { x, y } => format!("AND V{:X}, V{:X}", x, y),
+        Instruction::Xor { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+        Instruction::AddReg { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+        Instruction::SubReg { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+        Instruction::ShiftRight { x, .. } => format!("SHR V{:X}", x),
+        Instruction::SubN { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+        Instruction::ShiftLeft { x, .. } => format!("SHL V{:X}", x),
+        Instruction::SkipNeReg { x, y } => format!("SNE V{:X}, V{:X}", x, y),
+        Instruction::SetIndex(nnn) => format!("LD I, 0x{:03X}", nnn),
+        Instruction::JumpOffset(nnn) => format!("JP V0, 0x{:03X}", nnn),
+        Instruction::Random { x, nn } => format!("RND V{:X}, 0x{:02X}", x, nn),
+        Instruction::Draw { x, y, n } => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        Instruction::SkipKeyPressed { x } => format!("SKP V{:X}", x),
+        Instruction::SkipKeyReleased { x } => format!("SKNP V{:X}", x),
+        Instruction::LoadLongIndex => "LD I, LONG".to_string(),
+        Instruction::SelectPlanes(mask) => format!("PLANE {}", mask),
+        Instruction::LoadAudio => "AUDIO".to_string(),
+        Instruction::GetDelay { x } => format!("LD V{:X}, DT", x),
+        Instruction::WaitKey { x } => format!("LD V{:X}, K", x),
+        Instruction::SetDelay { x } => format!("LD DT, V{:X}", x),
+        Instruction::SetSound { x } => format!("LD ST, V{:X}", x),
+        Instruction::AddIndex { x } => format!("ADD I, V{:X}", x),
+        Instruction::FontChar { x } => format!("LD F, V{:X}", x),
+        Instruction::BigFontChar { x } => format!("LD HF, V{:X}", x),
+        Instruction::Bcd { x } => format!("LD B, V{:X}", x),
+        Instruction::SetPitch { x } => format!("PITCH V{:X}", x),
+        Instruction::StoreRegs { x } => format!("LD [I], V{:X}", x),
+        Instruction::LoadRegs { x } => format!("LD V{:X}, [I]", x),
+        Instruction::StoreFlags { x } => format!("LD R, V{:X}", x),
+        Instruction::LoadFlags { x } => format!("LD V{:X}, R", x),
+        Instruction::Unknown(op) => format!("DW 0x{:04X}", op),
+    }
+}
+
+/// Disassembles `count` instructions from `ram`, starting at address `start`.
+///
+/// Returns the address of every instruction alongside its mnemonic. `F000 NNNN` is
+/// treated as a single 4 byte instruction. Stops early when the end of `ram` is reached.
+pub fn disassemble_range(ram: &[u8], start: u16, count: usize) -> Vec<(u16, String)>
+{
+    let mut listing = Vec::with_capacity(count);
+    let mut addr = start as usize;
+
+    // Combine the higher byte and the lower byte at `addr` into a complete instruction
+    let word_at = |addr: usize| -> Option<u16> {
+        let higher_byte = *ram.get(addr)? as u16;
+        let lower_byte = *ram.get(addr + 1)? as u16;
+        Some((higher_byte << 8) | lower_byte)
+    };
+
+    while listing.len() < count {
+        let Some(op) = word_at(addr) else { break };
+
+        // The long load carries its address in the following 2 bytes
+        if op == 0xF000 {
+            if let Some(long_addr) = word_at(addr + 2) {
+                listing.push((addr as u16, format!("LD I, 0x{:04X}", long_addr)));
+                addr += 4;
+                continue;
+            }
+        }
+
+        listing.push((addr as u16, disassemble(op)));
+        addr += 2;
+    }
+
+    listing
+}
@@ -0,0 +1,41 @@
+use core::fmt;
+
+/// Chip8Error is an error raised while the emulator executes a program.
+///
+/// It contains the following variants:
+/// - `StackOverflow`: A CALL was executed while the stack was already full
+/// - `StackUnderflow`: A RET was executed while the stack was empty
+/// - `UnknownOpcode`: The instruction fetched from `addr` is not a valid opcode
+/// - `BadSnapshot`: A save state has the wrong header or version, or is malformed
+/// - `RomTooLarge`: The ROM, of the given size in bytes, doesn't fit in memory after the start address, or after a segment's address
+/// - `BadGzip`: A compressed ROM isn't valid gzip data
+/// - `AddressOutOfRange`: The address is past the end of the addressable memory
+/// - `RegisterOutOfRange`: The register index is past VF
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Error {
+    StackOverflow,
+    StackUnderflow,
+    UnknownOpcode { addr: u16, op: u16 },
+    BadSnapshot,
+    RomTooLarge(usize),
+    BadGzip,
+    AddressOutOfRange(u16),
+    RegisterOutOfRange(usize),
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::StackOverflow => write!(f, "stack overflow"),
+            Chip8Error::StackUnderflow => write!(f, "stack underflow"),
+            Chip8Error::UnknownOpcode { addr, op } => write!(f, "unknown opcode 0x{:04X} at 0x{:03X}", op, addr),
+            Chip8Error::BadSnapshot => write!(f, "invalid or unsupported save state"),
+            Chip8Error::RomTooLarge(len) => write!(f, "ROM of {} bytes doesn't fit in memory", len),
+            Chip8Error::BadGzip => write!(f, "invalid gzip data"),
+            Chip8Error::AddressOutOfRange(addr) => write!(f, "address 0x{:04X} is out of memory", addr),
+            Chip8Error::RegisterOutOfRange(idx) => write!(f, "there is no register V{:X}", idx),
+        }
+    }
+}
+
+impl core::error::Error for Chip8Error {}
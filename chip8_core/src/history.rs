@@ -0,0 +1,43 @@
+use crate::Emu;
+
+/// Upper bound for `enable_history`, every snapshot holds a full copy of RAM.
+pub const MAX_HISTORY: usize = 4096;
+
+impl Emu {
+    /// Starts recording a snapshot before every `tick`, keeping the last `capacity` of them.
+    ///
+    /// `capacity` is capped at `MAX_HISTORY`, and 0 turns recording off.
+    /// Changing the capacity drops the history recorded so far.
+    pub fn enable_history(&mut self, capacity: usize)
+    {
+        self.history_capacity = capacity.min(MAX_HISTORY);
+        self.history.clear();
+        self.history.reserve(self.history_capacity);
+    }
+
+    /// Restores the state from before the last recorded `tick`.
+    ///
+    /// Returns false and leaves the emulator untouched when there is nothing to step back to.
+    pub fn step_back(&mut self) -> bool
+    {
+        match self.history.pop_back() {
+            Some(snapshot) => self.load_state(&snapshot).is_ok(),
+            None => false,
+        }
+    }
+
+    // snapshot the current state, dropping the oldest one when the ring buffer is full
+    pub(crate) fn record_history(&mut self)
+    {
+        if self.history_capacity == 0 {
+            return;
+        }
+
+        if self.history.len() == self.history_capacity {
+            self.history.pop_front();
+        }
+
+        let snapshot = self.save_state();
+        self.history.push_back(snapshot);
+    }
+}
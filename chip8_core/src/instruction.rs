@@ -0,0 +1,253 @@
+/// Instruction is a decoded CHIP-8 opcode.
+///
+/// Register indices (`x`, `y`) are 0x0 to 0xF, `nn` is an 8 bit immediate,
+/// `nnn` is a 12 bit address and `n` is a 4 bit immediate.
+/// Each variant notes the opcode it's decoded from.
+///
+/// The 8XYN instructions that set a flag write VF after VX, so when X is F
+/// VF ends up holding the carry, borrow or shifted out bit rather than the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0000 - do nothing
+    Nop,
+    /// 00E0 - clear the screen
+    ClearScreen,
+    /// 00EE - return from a subroutine
+    Return,
+    /// 00CN - scroll the screen down N pixels (SUPER-CHIP)
+    ScrollDown(u8),
+    /// 00FB - scroll the screen right 4 pixels (SUPER-CHIP)
+    ScrollRight,
+    /// 00FC - scroll the screen left 4 pixels (SUPER-CHIP)
+    ScrollLeft,
+    /// 00FD - stop the program (SUPER-CHIP)
+    Exit,
+    /// 00FE - switch to the 64x32 resolution (SUPER-CHIP)
+    LowRes,
+    /// 00FF - switch to the 128x64 resolution (SUPER-CHIP)
+    HighRes,
+    /// 1NNN - jump to NNN
+    Jump(u16),
+    /// 2NNN - call the subroutine at NNN
+    Call(u16),
+    /// 3XNN - skip if VX == NN
+    SkipEqImm { x: u8, nn: u8 },
+    /// 4XNN - skip if VX != NN
+    SkipNeImm { x: u8, nn: u8 },
+    /// 5XY0 - skip if VX == VY
+    SkipEqReg { x: u8, y: u8 },
+    /// 6XNN - VX = NN
+    SetReg { x: u8, nn: u8 },
+    /// 7XNN - VX += NN
+    AddImm { x: u8, nn: u8 },
+    /// 8XY0 - VX = VY
+    Move { x: u8, y: u8 },
+    /// 8XY1 - VX |= VY
+    Or { x: u8, y: u8 },
+    /// 8XY2 - VX &= VY
+    And { x: u8, y: u8 },
+    /// 8XY3 - VX ^= VY
+    Xor { x: u8, y: u8 },
+    /// 8XY4 - VX += VY, VF = carry
+    AddReg { x: u8, y: u8 },
+    /// 8XY5 - VX -= VY, VF = not borrow
+    SubReg { x: u8, y: u8 },
+    /// 8XY6 - VX >>= 1, VF = shifted out bit
+    ShiftRight { x: u8, y: u8 },
+    /// 8XY7 - VX = VY - VX, VF = not borrow
+    SubN { x: u8, y: u8 },
+    /// 8XYE - VX <<= 1, VF = shifted out bit
+    ShiftLeft { x: u8, y: u8 },
+    /// 9XY0 - skip if VX != VY
+    SkipNeReg { x: u8, y: u8 },
+    /// ANNN - I = NNN
+    SetIndex(u16),
+    /// BNNN - jump to V0 + NNN
+    JumpOffset(u16),
+    /// CXNN - VX = rand() & NN
+    Random { x: u8, nn: u8 },
+    /// DXYN - draw an 8xN sprite at (VX, VY), or a 16x16 sprite when N is 0 (SUPER-CHIP)
+    Draw { x: u8, y: u8, n: u8 },
+    /// EX9E - skip if key VX is pressed, only the low nibble of VX is used
+    SkipKeyPressed { x: u8 },
+    /// EXA1 - skip if key VX is not pressed, only the low nibble of VX is used
+    SkipKeyReleased { x: u8 },
+    /// F000 NNNN - I = NNNN, read from the 2 bytes after the opcode (XO-CHIP)
+    LoadLongIndex,
+    /// FX01 - select the bitplanes to draw on (XO-CHIP)
+    SelectPlanes(u8),
+    /// F002 - load the 16 byte audio pattern at I (XO-CHIP)
+    LoadAudio,
+    /// FX07 - VX = DT
+    GetDelay { x: u8 },
+    /// FX0A - wait for a key and store it in VX
+    WaitKey { x: u8 },
+    /// FX15 - DT = VX
+    SetDelay { x: u8 },
+    /// FX18 - ST = VX
+    SetSound { x: u8 },
+    /// FX1E - I += VX
+    AddIndex { x: u8 },
+    /// FX29 - I = address of the small font glyph for VX
+    FontChar { x: u8 },
+    /// FX30 - I = address of the big font glyph for VX (SUPER-CHIP)
+    BigFontChar { x: u8 },
+    /// FX33 - store the BCD of VX at I, I + 1 and I + 2
+    Bcd { x: u8 },
+    /// FX3A - audio pitch = VX (XO-CHIP)
+    SetPitch { x: u8 },
+    /// FX55 - store V0 to VX at I
+    StoreRegs { x: u8 },
+    /// FX65 - load V0 to VX from I
+    LoadRegs { x: u8 },
+    /// FX75 - store V0 to VX in the RPL user flags (SUPER-CHIP)
+    StoreFlags { x: u8 },
+    /// FX85 - load V0 to VX from the RPL user flags (SUPER-CHIP)
+    LoadFlags { x: u8 },
+    /// Any opcode that isn't implemented
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// Returns the instruction's mnemonic with its operand shape, e.g. `LD Vx, byte` for 6XNN.
+    ///
+    /// This follows Cowgod's reference, so instructions sharing a mnemonic like `LD`
+    /// can still be told apart. Unknown opcodes are `DW`.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "NOP",
+            Instruction::ClearScreen => "CLS",
+            Instruction::Return => "RET",
+            Instruction::ScrollDown(_) => "SCD nibble",
+            Instruction::ScrollRight => "SCR",
+            Instruction::ScrollLeft => "SCL",
+            Instruction::Exit => "EXIT",
+            Instruction::LowRes => "LOW",
+            Instruction::HighRes => "HIGH",
+            Instruction::Jump(_) => "JP addr",
+            Instruction::Call(_) => "CALL addr",
+            Instruction::SkipEqImm { .. } => "SE Vx, byte",
+            Instruction::SkipNeImm { .. } => "SNE Vx, byte",
+            Instruction::SkipEqReg { .. } => "SE Vx, Vy",
+            Instruction::SetReg { .. } => "LD Vx, byte",
+            Instruction::AddImm { .. } => "ADD Vx, byte",
+            Instruction::Move { .. } => "LD Vx, Vy",
+            Instruction::Or { .. } => "OR Vx, Vy",
+            Instruction::And { .. } => "AND Vx, Vy",
+            Instruction::Xor { .. } => "XOR Vx, Vy",
+            Instruction::AddReg { .. } => "ADD Vx, Vy",
+            Instruction::SubReg { .. } => "SUB Vx, Vy",
+            Instruction::ShiftRight { .. } => "SHR Vx",
+            Instruction::SubN { .. } => "SUBN Vx, Vy",
+            Instruction::ShiftLeft { .. } => "SHL Vx",
+            Instruction::SkipNeReg { .. } => "SNE Vx, Vy",
+            Instruction::SetIndex(_) => "LD I, addr",
+            Instruction::JumpOffset(_) => "JP V0, addr",
+            Instruction::Random { .. } => "RND Vx, byte",
+            Instruction::Draw { .. } => "DRW Vx, Vy, nibble",
+            Instruction::SkipKeyPressed { .. } => "SKP Vx",
+            Instruction::SkipKeyReleased { .. } => "SKNP Vx",
+            Instruction::LoadLongIndex => "LD I, long",
+            Instruction::SelectPlanes(_) => "PLANE n",
+            Instruction::LoadAudio => "AUDIO",
+            Instruction::GetDelay { .. } => "LD Vx, DT",
+            Instruction::WaitKey { .. } => "LD Vx, K",
+            Instruction::SetDelay { .. } => "LD DT, Vx",
+            Instruction::SetSound { .. } => "LD ST, Vx",
+            Instruction::AddIndex { .. } => "ADD I, Vx",
+            Instruction::FontChar { .. } => "LD F, Vx",
+            Instruction::BigFontChar { .. } => "LD HF, Vx",
+            Instruction::Bcd { .. } => "LD B, Vx",
+            Instruction::SetPitch { .. } => "PITCH Vx",
+            Instruction::StoreRegs { .. } => "LD [I], Vx",
+            Instruction::LoadRegs { .. } => "LD Vx, [I]",
+            Instruction::StoreFlags { .. } => "LD R, Vx",
+            Instruction::LoadFlags { .. } => "LD Vx, R",
+            Instruction::Unknown(_) => "DW",
+        }
+    }
+}
+
+/// Decodes a 16 bit opcode into an `Instruction`.
+///
+/// The opcode is split into four parts: digit1, digit2, digit3, and digit4.
+/// The first digit selects the instruction group, and only the groups that share it
+/// look at the remaining digits, so the common opcodes take a single branch.
+pub fn decode(op: u16) -> Instruction
+{
+    // Split operation code to four parts
+    let digit1 = (op & 0xF000) >> 12;
+    let digit2 = (op & 0x0F00) >> 8;
+    let digit3 = (op & 0x00F0) >> 4;
+    let digit4 = op & 0x000F;
+
+    let x = digit2 as u8;
+    let y = digit3 as u8;
+    let n = digit4 as u8;
+    let nn = (op & 0xFF) as u8;
+    let nnn = op & 0xFFF;
+
+    match digit1 {
+        0 => match op {
+            0x0000 => Instruction::Nop,
+            0x00E0 => Instruction::ClearScreen,
+            0x00EE => Instruction::Return,
+            0x00C0..=0x00CF => Instruction::ScrollDown(n),
+            0x00FB => Instruction::ScrollRight,
+            0x00FC => Instruction::ScrollLeft,
+            0x00FD => Instruction::Exit,
+            0x00FE => Instruction::LowRes,
+            0x00FF => Instruction::HighRes,
+            _ => Instruction::Unknown(op),
+        },
+        1 => Instruction::Jump(nnn),
+        2 => Instruction::Call(nnn),
+        3 => Instruction::SkipEqImm { x, nn },
+        4 => Instruction::SkipNeImm { x, nn },
+        5 if digit4 == 0 => Instruction::SkipEqReg { x, y },
+        6 => Instruction::SetReg { x, nn },
+        7 => Instruction::AddImm { x, nn },
+        8 => match digit4 {
+            0 => Instruction::Move { x, y },
+            1 => Instruction::Or { x, y },
+            2 => Instruction::And { x, y },
+            3 => Instruction::Xor { x, y },
+            4 => Instruction::AddReg { x, y },
+            5 => Instruction::SubReg { x, y },
+            6 => Instruction::ShiftRight { x, y },
+            7 => Instruction::SubN { x, y },
+            0xE => Instruction::ShiftLeft { x, y },
+            _ => Instruction::Unknown(op),
+        },
+        9 if digit4 == 0 => Instruction::SkipNeReg { x, y },
+        0xA => Instruction::SetIndex(nnn),
+        0xB => Instruction::JumpOffset(nnn),
+        0xC => Instruction::Random { x, nn },
+        0xD => Instruction::Draw { x, y, n },
+        0xE => match nn {
+            0x9E => Instruction::SkipKeyPressed { x },
+            0xA1 => Instruction::SkipKeyReleased { x },
+            _ => Instruction::Unknown(op),
+        },
+        0xF => match (op, nn) {
+            (0xF000, _) => Instruction::LoadLongIndex,
+            (0xF002, _) => Instruction::LoadAudio,
+            (_, 0x01) => Instruction::SelectPlanes(x),
+            (_, 0x07) => Instruction::GetDelay { x },
+            (_, 0x0A) => Instruction::WaitKey { x },
+            (_, 0x15) => Instruction::SetDelay { x },
+            (_, 0x18) => Instruction::SetSound { x },
+            (_, 0x1E) => Instruction::AddIndex { x },
+            (_, 0x29) => Instruction::FontChar { x },
+            (_, 0x30) => Instruction::BigFontChar { x },
+            (_, 0x33) => Instruction::Bcd { x },
+            (_, 0x3A) => Instruction::SetPitch { x },
+            (_, 0x55) => Instruction::StoreRegs { x },
+            (_, 0x65) => Instruction::LoadRegs { x },
+            (_, 0x75) => Instruction::StoreFlags { x },
+            (_, 0x85) => Instruction::LoadFlags { x },
+            _ => Instruction::Unknown(op),
+        },
+        _ => Instruction::Unknown(op),
+    }
+}
@@ -0,0 +1,50 @@
+use crate::NUM_KEYS;
+
+/// Key is one of the 16 keys of the CHIP-8 hex keypad, laid out as
+///
+/// ```text
+/// 1 2 3 C
+/// 4 5 6 D
+/// 7 8 9 E
+/// A 0 B F
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Key {
+    Key0 = 0x0,
+    Key1 = 0x1,
+    Key2 = 0x2,
+    Key3 = 0x3,
+    Key4 = 0x4,
+    Key5 = 0x5,
+    Key6 = 0x6,
+    Key7 = 0x7,
+    Key8 = 0x8,
+    Key9 = 0x9,
+    KeyA = 0xA,
+    KeyB = 0xB,
+    KeyC = 0xC,
+    KeyD = 0xD,
+    KeyE = 0xE,
+    KeyF = 0xF,
+}
+
+impl Key {
+    /// Every key, in index order.
+    pub const ALL: [Key; NUM_KEYS] = [
+        Key::Key0, Key::Key1, Key::Key2, Key::Key3,
+        Key::Key4, Key::Key5, Key::Key6, Key::Key7,
+        Key::Key8, Key::Key9, Key::KeyA, Key::KeyB,
+        Key::KeyC, Key::KeyD, Key::KeyE, Key::KeyF,
+    ];
+
+    // the index used by EX9E, EXA1 and FX0A
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    // the key with the given index, or None when the index is 0x10 or more
+    pub fn from_index(idx: usize) -> Option<Key> {
+        Key::ALL.get(idx).copied()
+    }
+}
@@ -1,15 +1,112 @@
-use rand::random;
+//! A CHIP-8, SUPER-CHIP and XO-CHIP emulator core with no platform dependencies.
+//!
+//! Without the default `std` feature the crate is `no_std`, and the bare interpreter doesn't
+//! allocate at all. The `alloc` feature adds the parts that live on the heap: save states,
+//! the rewind history, breakpoints, hooks, the disassembler and `get_display`. Builds without
+//! `std` also have no OS entropy, so seed the random number generator with `Emu::with_seed`
+//! or `reseed`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::collections::{BTreeSet, VecDeque};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+mod accumulate;
+#[cfg(feature = "alloc")]
+mod analysis;
+#[cfg(feature = "alloc")]
+pub mod asm;
+mod builder;
+mod crc32;
+#[cfg(feature = "alloc")]
+mod debug;
+mod diff;
+#[cfg(feature = "alloc")]
+pub mod disasm;
+mod error;
+#[cfg(feature = "alloc")]
+mod history;
+mod instruction;
+mod key;
+#[cfg(feature = "std")]
+mod pbm;
+mod phosphor;
+mod quirks;
+#[cfg(feature = "alloc")]
+mod replay;
+#[cfg(feature = "alloc")]
+mod snapshot;
+mod stack;
+mod summary;
+#[cfg(test)]
+mod tests;
+mod timing;
+
+pub use builder::EmuBuilder;
+#[cfg(feature = "alloc")]
+pub use debug::StopReason;
+pub use error::Chip8Error;
+#[cfg(feature = "alloc")]
+pub use history::MAX_HISTORY;
+pub use instruction::{decode, Instruction};
+pub use key::Key;
+pub use quirks::{detect_quirks, Quirks};
+#[cfg(feature = "alloc")]
+pub use replay::{InputEvent, InputLog};
+pub use stack::StackOverflowPolicy;
+pub use timing::{cycle_cost, CycleCost, VIP_CYCLES_PER_FRAME};
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+pub const HIRES_SCREEN_WIDTH: usize = 128;
+pub const HIRES_SCREEN_HEIGHT: usize = 64;
+const SCREEN_SIZE: usize = HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT;
+// The screen is stored 64 pixels per word
+const SCREEN_WORDS: usize = SCREEN_SIZE / 64;
+pub const NUM_PLANES: usize = 2;
+/// Rate at which frontends should call `tick_timers`, the timers count down at 60Hz.
+///
+/// The CPU runs `ticks_per_frame` instructions between two timer ticks,
+/// so its speed is `ticks_per_frame * TIMER_HZ` instructions per second.
+pub const TIMER_HZ: u32 = 60;
+/// CPU speed a new `Emu` targets, 10 instructions per frame.
+pub const DEFAULT_TARGET_IPS: u32 = 600;
 const RAM_SIZE: usize = 4096;
+// XO-CHIP programs can address the full 16 bits
+const LARGE_RAM_SIZE: usize = 0x10000;
 const NUM_REGS: usize = 16;
 const STACK_SIZE: usize = 16;
+/// The largest stack `Emu::set_stack_size` allows.
+pub const MAX_STACK_SIZE: usize = 256;
 const NUM_KEYS: usize = 16;
+pub const NUM_FLAGS: usize = 8;
+pub const AUDIO_BUFFER_SIZE: usize = 16;
+// XO-CHIP pitch register value for a 4000Hz playback rate
+const DEFAULT_AUDIO_PITCH: u8 = 64;
+// Where ROMs are loaded and PC starts, unless set_start_address moves it
 const START_ADDR: u16 = 0x200;
-const FONTSET_SIZE: usize = 80;
+// Without std there is no OS entropy, so the generator starts from a fixed seed
+#[cfg(not(feature = "std"))]
+const DEFAULT_SEED: u64 = 0xC8;
+pub const FONTSET_SIZE: usize = 80;
+pub const BIG_FONTSET_SIZE: usize = 160;
+/// Where FX29 finds the small font, anywhere below 0x200 works.
+pub const FONTSET_START_ADDR: u16 = 0x000;
+/// Where FX30 finds the SUPER-CHIP big font, right after the small one.
+pub const BIG_FONTSET_START_ADDR: u16 = FONTSET_START_ADDR + FONTSET_SIZE as u16;
 
-const FONTSET: [u8; FONTSET_SIZE] = [
+/// The built-in font for the hex digits 0 to F, 5 bytes per 4x5 glyph.
+///
+/// Every emulator has it in RAM at `FONTSET_START_ADDR`, the program can't rely on anything else.
+pub const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -28,100 +125,950 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80  // F
 ];
 
+/// The SUPER-CHIP big font for the digits 0 to F, 10 bytes per 8x10 glyph, in RAM at
+/// `BIG_FONTSET_START_ADDR`.
+pub const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+/// Resolution is the display mode, switched by 00FE (LOW) and 00FF (HIGH).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// The original 64x32 display
+    Lores,
+    /// The SUPER-CHIP 128x64 display
+    Hires,
+}
 
 /// Emu is a struct representing an emulator.
 ///
 /// It contains the following fields:
 /// - `pc`: Represents the program counter
 /// - `ram`: Represents the random-access memory
-/// - `screen`: Represents the emulator's screen
+/// - `screen`: Represents the emulator's screen, one bitset per XO-CHIP bitplane
 /// - `v_reg`: Represents the general purpose registers
 /// - `i_reg`: Represents the index register
 /// - `sp`: Represents the stack pointer
-/// - `stack`: Represents the stack
+/// - `stack`: Represents the stack, only the first `stack_size` entries are used
+/// - `stack_size`: Represents how many return addresses the stack holds
+/// - `stack_overflow_policy`: Represents what a CALL does when the stack is full
 /// - `keys`: Represents the emulator's key input
 /// - `dt`: Represents the delay timer
 /// - `st`: Represents the sound timer
+/// - `hires`: Represents whether the SUPER-CHIP 128x64 resolution is active
+/// - `flags`: Represents the SUPER-CHIP RPL user flags
+/// - `plane_mask`: Represents the XO-CHIP bitplanes selected for drawing
+/// - `large_memory`: Represents whether the XO-CHIP 64KB address space is enabled
+/// - `audio_buffer`: Represents the XO-CHIP 1-bit audio pattern
+/// - `audio_pitch`: Represents the XO-CHIP audio pattern playback pitch
+/// - `quirks`: Represents the interpreter behaviors selected for compatibility
+/// - `target_ips`: Represents the instructions per second frontends should run
+/// - `waiting_for_vblank`: Represents whether a DRAW is waiting for the next frame, with the `display_wait` quirk
+/// - `phosphor_decay`: Represents how fast unlit pixels fade out, 0 when phosphor persistence is off
+/// - `intensity`: Represents the brightness of every pixel of the first plane, with phosphor persistence
+/// - `drawn_screen`: Represents every pixel lit since the last frame, with the `accumulate_draws` quirk
+/// - `visible_screen`: Represents the screen shown to frontends, with the `accumulate_draws` quirk
+/// - `screen_dirty`: Represents whether the screen changed since `take_screen_dirty` was last called
+/// - `halted`: Represents whether the program stopped itself with 00FD (EXIT)
+/// - `rng`: Represents the random number generator used by CXNN
+/// - `waiting_for_key`: Represents the register a WAIT KEY stores the key in while it waits for one
+/// - `held_key`: Represents the key pressed during WAIT KEY, which has to be released before it is reported
+/// - `history`: Represents the snapshots recorded before each tick, for stepping back
+/// - `history_capacity`: Represents how many snapshots `history` keeps, 0 when disabled
+/// - `breakpoints`: Represents the addresses `tick_until_breakpoint` stops at
+/// - `trace_hook`: Represents the callback invoked with the address and opcode of every fetched instruction
+/// - `beep_callback`: Represents the callback told when the beep starts and stops
+/// - `input_log`: Represents the key presses being recorded, see `start_input_log`
+/// - `replay`: Represents the recorded key presses still to be replayed
+/// - `start_addr`: Represents where ROMs are loaded and PC starts, 0x200 unless changed
+/// - `rom_len`: Represents the size of the ROM loaded at `start_addr`
+/// - `rom_crc`: Represents the CRC-32 of the ROM as it was loaded, `None` before a ROM is loaded
+/// - `cycles`: Represents the number of instructions executed
+/// - `ticks`: Represents the number of `try_tick` calls, including the ones that waited, replays are stamped with it
+/// - `frames`: Represents the number of times the timers were ticked
+/// - `cycle_cost`: Represents the instruction timings `hw_cycles` counts with, `None` when off
+/// - `hw_cycles`: Represents the machine cycles executed, see `set_cycle_cost`
+/// - `frame_start_hw_cycles`: Represents `hw_cycles` at the last vertical blank
+///
+/// With the `serde` feature enabled the whole machine can be serialized for save states.
+/// The random number generator isn't part of the snapshot, a restored `Emu` gets a fresh one.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Emu {
     pc: u16,
-    ram: [u8; RAM_SIZE],
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    ram: [u8; LARGE_RAM_SIZE],
+    #[cfg_attr(feature = "serde", serde(with = "planes_serde"))]
+    screen: [[u64; SCREEN_WORDS]; NUM_PLANES],
     v_reg: [u8; NUM_REGS],
     i_reg: u16,
     sp: u16,
-    stack: [u16; STACK_SIZE],
+    #[cfg_attr(feature = "serde", serde(with = "serde_big_array::BigArray"))]
+    stack: [u16; MAX_STACK_SIZE],
+    stack_size: usize,
+    stack_overflow_policy: StackOverflowPolicy,
     keys: [bool; NUM_KEYS],
     dt: u8,
     st: u8,
+    hires: bool,
+    flags: [u8; NUM_FLAGS],
+    plane_mask: u8,
+    large_memory: bool,
+    audio_buffer: [u8; AUDIO_BUFFER_SIZE],
+    audio_pitch: u8,
+    quirks: Quirks,
+    target_ips: u32,
+    start_addr: u16,
+    waiting_for_vblank: bool,
+    halted: bool,
+    screen_dirty: bool,
+    phosphor_decay: u8,
+    #[cfg_attr(feature = "serde", serde(skip, default = "no_intensity"))]
+    intensity: [u8; SCREEN_SIZE],
+    #[cfg_attr(feature = "serde", serde(skip, default = "no_planes"))]
+    drawn_screen: [[u64; SCREEN_WORDS]; NUM_PLANES],
+    #[cfg_attr(feature = "serde", serde(skip, default = "no_planes"))]
+    visible_screen: [[u64; SCREEN_WORDS]; NUM_PLANES],
+    #[cfg_attr(feature = "serde", serde(skip, default = "default_rng"))]
+    rng: SmallRng,
+    waiting_for_key: Option<usize>,
+    held_key: Option<usize>,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history: VecDeque<Vec<u8>>,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history_capacity: usize,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    breakpoints: BTreeSet<u16>,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    trace_hook: Option<TraceHook>,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    beep_callback: Option<BeepCallback>,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    input_log: Option<InputLog>,
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    replay: VecDeque<InputEvent>,
+    rom_len: usize,
+    rom_crc: Option<u32>,
+    cycles: u64,
+    ticks: u64,
+    frames: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    cycle_cost: Option<CycleCost>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hw_cycles: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    frame_start_hw_cycles: u64,
+}
+
+// unpack a screen bitset into one bool per pixel, the most significant bit first
+#[cfg(feature = "alloc")]
+fn unpack_pixels(words: &[u64]) -> Vec<bool> {
+    (0..words.len() * 64)
+        .map(|idx| words[idx / 64] & (1 << (63 - idx % 64)) != 0)
+        .collect()
+}
+
+// seed the random number generator from the OS
+#[cfg(feature = "std")]
+fn default_rng() -> SmallRng {
+    SmallRng::from_entropy()
+}
+
+// without std there is no entropy source, use with_seed or reseed to supply one
+#[cfg(not(feature = "std"))]
+fn default_rng() -> SmallRng {
+    SmallRng::seed_from_u64(DEFAULT_SEED)
+}
+
+// serde can't default arrays this large, the intensities are rebuilt by the next frame anyway
+#[cfg(feature = "serde")]
+fn no_intensity() -> [u8; SCREEN_SIZE] {
+    [0; SCREEN_SIZE]
+}
+
+// like the intensities, the accumulated frame is rebuilt by the next frame
+#[cfg(feature = "serde")]
+fn no_planes() -> [[u64; SCREEN_WORDS]; NUM_PLANES] {
+    [[0; SCREEN_WORDS]; NUM_PLANES]
+}
+
+/// TraceHook is called with the address and the opcode of every instruction `tick` fetches.
+#[cfg(feature = "alloc")]
+pub type TraceHook = Box<dyn FnMut(u16, u16)>;
+
+/// BeepCallback is called with true when the beep starts and false when it stops.
+#[cfg(feature = "alloc")]
+pub type BeepCallback = Box<dyn FnMut(bool)>;
+
+// serde only supports arrays up to 32 elements, so every plane is wrapped in a BigArray
+#[cfg(feature = "serde")]
+mod planes_serde {
+    use super::{NUM_PLANES, SCREEN_WORDS};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde_big_array::Array;
+
+    pub fn serialize<S: Serializer>(planes: &[[u64; SCREEN_WORDS]; NUM_PLANES], serializer: S) -> Result<S::Ok, S::Error> {
+        planes.map(Array).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[[u64; SCREEN_WORDS]; NUM_PLANES], D::Error> {
+        let planes = <[Array<u64, SCREEN_WORDS>; NUM_PLANES]>::deserialize(deserializer)?;
+        Ok(planes.map(|plane| plane.0))
+    }
+}
+
+impl Default for Emu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cloning copies the whole machine, including the random number generator and the history.
+///
+/// The trace hook and the beep callback can't be cloned, the clone starts without them.
+impl Clone for Emu {
+    fn clone(&self) -> Self {
+        Self {
+            pc: self.pc,
+            ram: self.ram,
+            screen: self.screen,
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            sp: self.sp,
+            stack: self.stack,
+            stack_size: self.stack_size,
+            stack_overflow_policy: self.stack_overflow_policy,
+            keys: self.keys,
+            dt: self.dt,
+            st: self.st,
+            hires: self.hires,
+            flags: self.flags,
+            plane_mask: self.plane_mask,
+            large_memory: self.large_memory,
+            audio_buffer: self.audio_buffer,
+            audio_pitch: self.audio_pitch,
+            quirks: self.quirks,
+            target_ips: self.target_ips,
+            start_addr: self.start_addr,
+            waiting_for_vblank: self.waiting_for_vblank,
+            halted: self.halted,
+            screen_dirty: self.screen_dirty,
+            phosphor_decay: self.phosphor_decay,
+            intensity: self.intensity,
+            drawn_screen: self.drawn_screen,
+            visible_screen: self.visible_screen,
+            rng: self.rng.clone(),
+            waiting_for_key: self.waiting_for_key,
+            held_key: self.held_key,
+            #[cfg(feature = "alloc")]
+            history: self.history.clone(),
+            #[cfg(feature = "alloc")]
+            history_capacity: self.history_capacity,
+            #[cfg(feature = "alloc")]
+            breakpoints: self.breakpoints.clone(),
+            #[cfg(feature = "alloc")]
+            trace_hook: None,
+            #[cfg(feature = "alloc")]
+            beep_callback: None,
+            #[cfg(feature = "alloc")]
+            input_log: self.input_log.clone(),
+            #[cfg(feature = "alloc")]
+            replay: self.replay.clone(),
+            rom_len: self.rom_len,
+            rom_crc: self.rom_crc,
+            cycles: self.cycles,
+            ticks: self.ticks,
+            frames: self.frames,
+            cycle_cost: self.cycle_cost,
+            hw_cycles: self.hw_cycles,
+            frame_start_hw_cycles: self.frame_start_hw_cycles,
+        }
+    }
 }
 
 impl Emu {
+    /// Creates an emulator with the default settings, use `EmuBuilder` to configure one.
     pub fn new() -> Self {
         let mut new_emu = Self {
             pc: START_ADDR,
-            ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            ram: [0; LARGE_RAM_SIZE],
+            screen: [[0; SCREEN_WORDS]; NUM_PLANES],
             v_reg: [0; NUM_REGS],
             i_reg: 0,
             sp: 0,
-            stack: [0; STACK_SIZE],
+            stack: [0; MAX_STACK_SIZE],
+            stack_size: STACK_SIZE,
+            stack_overflow_policy: StackOverflowPolicy::Error,
             keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
+            hires: false,
+            flags: [0; NUM_FLAGS],
+            plane_mask: 1,
+            large_memory: false,
+            audio_buffer: [0; AUDIO_BUFFER_SIZE],
+            audio_pitch: DEFAULT_AUDIO_PITCH,
+            quirks: Quirks::default(),
+            target_ips: DEFAULT_TARGET_IPS,
+            start_addr: START_ADDR,
+            waiting_for_vblank: false,
+            halted: false,
+            screen_dirty: true,
+            phosphor_decay: 0,
+            intensity: [0; SCREEN_SIZE],
+            drawn_screen: [[0; SCREEN_WORDS]; NUM_PLANES],
+            visible_screen: [[0; SCREEN_WORDS]; NUM_PLANES],
+            rng: default_rng(),
+            waiting_for_key: None,
+            held_key: None,
+            #[cfg(feature = "alloc")]
+            history: VecDeque::new(),
+            #[cfg(feature = "alloc")]
+            history_capacity: 0,
+            #[cfg(feature = "alloc")]
+            breakpoints: BTreeSet::new(),
+            #[cfg(feature = "alloc")]
+            trace_hook: None,
+            #[cfg(feature = "alloc")]
+            beep_callback: None,
+            #[cfg(feature = "alloc")]
+            input_log: None,
+            #[cfg(feature = "alloc")]
+            replay: VecDeque::new(),
+            rom_len: 0,
+            rom_crc: None,
+            cycles: 0,
+            ticks: 0,
+            frames: 0,
+            cycle_cost: None,
+            hw_cycles: 0,
+            frame_start_hw_cycles: 0,
         };
 
-        // Copy FONTSET to RAM from first to 80
-        new_emu.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        new_emu.load_fonts();
 
         new_emu
     }
 
-    fn push(&mut self, val: u16)
-    {
-        self.stack[self.sp as usize] = val;
+    /// Creates an emulator whose CXNN results are fully determined by `seed`.
+    ///
+    /// Two emulators built with the same seed and fed the same input produce the same run,
+    /// which makes deterministic replays possible.
+    ///
+    /// Without the `std` feature `new` always uses the same seed, as there is no OS entropy,
+    /// so `no_std` builds should supply their own seed here or through `reseed`.
+    pub fn with_seed(seed: u64) -> Self {
+        let mut new_emu = Self::new();
+        new_emu.reseed(seed);
+        new_emu
+    }
 
-        self.sp += 1;
+    /// Creates an emulator with `data` loaded at 0x200, the default start address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::RomTooLarge` if the ROM doesn't fit in the 4KB address space.
+    pub fn from_rom(data: &[u8]) -> Result<Self, Chip8Error> {
+        let mut new_emu = Self::new();
+        new_emu.check_rom_size(data.len())?;
+        new_emu.load(data);
+        Ok(new_emu)
     }
 
-    fn pop(&mut self) -> u16
-    {
-        self.sp -= 1;
+    /// Restarts the random number generator from `seed`.
+    ///
+    /// `reset` keeps the generator's state, so call this after it to replay a run.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = SmallRng::seed_from_u64(seed);
+    }
+
+    // return the array of display (first plane), sized to the active resolution
+    #[cfg(feature = "alloc")]
+    pub fn get_display(&self) -> Vec<bool> {
+        unpack_pixels(self.get_display_bits())
+    }
+
+    /// Lists the pixels of the first plane that differ from `prev`, a frame the caller got
+    /// from `get_display` earlier, as `(index, lit)` pairs in index order.
+    ///
+    /// Pixels past the end of `prev` count as unlit. After a resolution switch the indices
+    /// no longer line up, so remote renderers should send the whole frame instead.
+    #[cfg(feature = "alloc")]
+    pub fn screen_delta(&self, prev: &[bool]) -> Vec<(usize, bool)> {
+        self.get_display()
+            .into_iter()
+            .enumerate()
+            .filter(|&(idx, lit)| prev.get(idx).copied().unwrap_or(false) != lit)
+            .collect()
+    }
+
+    /// Returns true if the screen changed since the last call, and clears the flag.
+    ///
+    /// DRAW, CLS, the scrolls and resolution switches set it, as do `reset` and `load_state`.
+    /// A new emulator starts dirty, so frontends draw the first frame and can skip
+    /// redrawing the following ones until this returns true again.
+    pub fn take_screen_dirty(&mut self) -> bool {
+        core::mem::take(&mut self.screen_dirty)
+    }
+
+    /// Returns the first plane packed 64 pixels per word, sized to the active resolution.
+    ///
+    /// The most significant bit of a word is its leftmost pixel, and rows follow each other
+    /// from the top, so a row is one word in 64x32 and two words in 128x64.
+    pub fn get_display_bits(&self) -> &[u64] {
+        &self.display_planes()[0][..self.screen_words()]
+    }
+
+    // return every XO-CHIP bitplane, sized to the active resolution
+    // a pixel's color index is (plane 2 << 1) | plane 1
+    #[cfg(feature = "alloc")]
+    pub fn get_display_planes(&self) -> [Vec<bool>; NUM_PLANES] {
+        let words = self.screen_words();
+        let planes = self.display_planes();
+        [unpack_pixels(&planes[0][..words]), unpack_pixels(&planes[1][..words])]
+    }
+
+    /// Returns the `(x, y)` coordinates of every lit pixel of the first plane, row by row.
+    ///
+    /// Coordinates are in the active resolution. Only set bits are visited, so this is
+    /// cheaper than scanning `get_display` when most of the screen is dark.
+    pub fn lit_pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.screen_width();
+
+        self.get_display_bits()
+            .iter()
+            .enumerate()
+            .flat_map(move |(word_idx, &word)| {
+                let mut bits = word;
+                core::iter::from_fn(move || {
+                    if bits == 0 {
+                        return None;
+                    }
+
+                    // The most significant bit is the leftmost pixel
+                    let offset = bits.leading_zeros() as usize;
+                    bits &= !(1 << (63 - offset));
+
+                    let idx = word_idx * 64 + offset;
+                    Some((idx % width, idx / width))
+                })
+            })
+    }
+
+    /// Renders the first plane into an RGBA buffer, ready to upload to a texture or `ImageData`.
+    ///
+    /// Every pixel becomes a `scale` x `scale` block of `fg` when lit and `bg` otherwise.
+    /// The buffer is `width * scale` pixels wide and `height * scale` pixels high in the
+    /// active resolution, 4 bytes per pixel, row by row from the top left corner.
+    #[cfg(feature = "alloc")]
+    pub fn render_rgba(&self, fg: [u8; 4], bg: [u8; 4], scale: usize) -> Vec<u8> {
+        let width = self.screen_width();
+        let height = self.screen_height();
+        let display = self.get_display();
+
+        let mut buffer = Vec::with_capacity(width * scale * height * scale * 4);
+        for y in 0..height * scale {
+            for x in 0..width * scale {
+                let lit = display[(y / scale) * width + x / scale];
+                buffer.extend_from_slice(if lit { &fg } else { &bg });
+            }
+        }
+
+        buffer
+    }
+
+    // number of screen words used by the active resolution
+    fn screen_words(&self) -> usize {
+        self.screen_width() * self.screen_height() / 64
+    }
 
-        self.stack[self.sp as usize]
+    // read row y of a plane with the leftmost pixel in the most significant bit,
+    // in 64x32 the lower half is always zero
+    fn row(&self, plane: usize, y: usize) -> u128 {
+        let words = &self.screen[plane];
+        if self.hires {
+            (words[2 * y] as u128) << 64 | words[2 * y + 1] as u128
+        } else {
+            (words[y] as u128) << 64
+        }
     }
 
-    // return the array of display
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    // write row y of a plane, pixels past the right edge are dropped
+    fn set_row(&mut self, plane: usize, y: usize, row: u128) {
+        // A clipped sprite or a scroll leaves bits past the edge, which would otherwise
+        // light the start of the next row of intensities
+        let row = row & !u128::MAX.checked_shr(self.screen_width() as u32).unwrap_or(0);
+
+        if plane == 0 && self.phosphor_decay > 0 {
+            self.light_row(y, self.row(0, y), row);
+        }
+
+        let words = &mut self.screen[plane];
+        if self.hires {
+            words[2 * y] = (row >> 64) as u64;
+            words[2 * y + 1] = row as u64;
+        } else {
+            words[y] = (row >> 64) as u64;
+        }
+
+        if self.quirks.accumulate_draws {
+            self.accumulate_row(plane, y);
+        }
+        self.screen_dirty = true;
     }
 
-    // handle key press
+    // return the active display mode
+    pub fn resolution(&self) -> Resolution {
+        if self.hires { Resolution::Hires } else { Resolution::Lores }
+    }
+
+    /// Returns the `(width, height)` of the active resolution in pixels.
+    ///
+    /// The display getters are sized to match, so frontends should scale by this
+    /// rather than assume 64x32.
+    pub fn screen_dimensions(&self) -> (usize, usize) {
+        (self.screen_width(), self.screen_height())
+    }
+
+    // width of the active resolution
+    fn screen_width(&self) -> usize {
+        if self.hires { HIRES_SCREEN_WIDTH } else { SCREEN_WIDTH }
+    }
+
+    // height of the active resolution
+    fn screen_height(&self) -> usize {
+        if self.hires { HIRES_SCREEN_HEIGHT } else { SCREEN_HEIGHT }
+    }
+
+    // handle key press, indices past 0xF are ignored
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
         // set pressed key to true
-        self.keys[idx] = pressed;
+        if let Some(key) = self.keys.get_mut(idx) {
+            *key = pressed;
+            #[cfg(feature = "alloc")]
+            self.record_input(idx, pressed);
+        }
+    }
+
+    // handle key press for a typed key, which can't be out of range
+    pub fn set_key(&mut self, key: Key, pressed: bool) {
+        self.keypress(key.index(), pressed);
+    }
+
+    // return whether key idx is held, indices past 0xF are never pressed
+    pub fn is_key_pressed(&self, idx: usize) -> bool {
+        self.keys.get(idx).copied().unwrap_or(false)
+    }
+
+    // return the indices of the held keys, in increasing order
+    pub fn pressed_keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.keys.iter().enumerate().filter(|&(_, &pressed)| pressed).map(|(idx, _)| idx)
+    }
+
+    // return the state of the whole keypad, indexed by key
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
     }
 
-    // load game code from file into our RAM
+    // return the program counter
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // return the value of register V{idx}, None past VF
+    pub fn v_reg(&self, idx: usize) -> Option<u8> {
+        self.v_reg.get(idx).copied()
+    }
+
+    /// Overwrites register V{idx}, e.g. from a debugger or a cheat.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::RegisterOutOfRange` if `idx` is past VF.
+    pub fn set_v_reg(&mut self, idx: usize, val: u8) -> Result<(), Chip8Error> {
+        let reg = self.v_reg.get_mut(idx).ok_or(Chip8Error::RegisterOutOfRange(idx))?;
+        *reg = val;
+        Ok(())
+    }
+
+    // return all general purpose registers, V0 to VF
+    pub fn registers(&self) -> &[u8; NUM_REGS] {
+        &self.v_reg
+    }
+
+    // return the index register
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    // overwrite the index register
+    pub fn set_i_reg(&mut self, val: u16) {
+        self.i_reg = val;
+    }
+
+    // return the stack pointer
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    // return the return addresses currently on the stack, oldest first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    // return the delay timer
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    // return the sound timer
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    // set the delay timer, for debuggers and cheats
+    pub fn set_delay_timer(&mut self, dt: u8) {
+        self.dt = dt;
+    }
+
+    // set the sound timer like FX18 does, so the beep callback still hears about it
+    pub fn set_sound_timer(&mut self, st: u8) {
+        self.update_sound_timer(st);
+    }
+
+    // return the number of instructions executed since the last reset
+    pub fn cycle_count(&self) -> u64 {
+        self.cycles
+    }
+
+    // return the number of timer ticks (frames) since the last reset
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    // return the byte stored in RAM at addr
+    pub fn peek_ram(&self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    /// Borrows the whole addressable memory, for hex viewers and external disassemblers.
+    ///
+    /// It's 4KB long, or 64KB with large memory enabled, and indexed by address, so the ROM
+    /// starts at `start_address`.
+    pub fn ram_snapshot(&self) -> &[u8] {
+        &self.ram[..self.memory_size()]
+    }
+
+    /// Writes `val` to RAM at `addr`, the write counterpart of `peek_ram` for debuggers and cheats.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::AddressOutOfRange` if `addr` is past the addressable memory,
+    /// 4KB unless large memory is enabled.
+    pub fn poke_ram(&mut self, addr: u16, val: u8) -> Result<(), Chip8Error> {
+        if addr as usize >= self.memory_size() {
+            return Err(Chip8Error::AddressOutOfRange(addr));
+        }
+
+        self.ram[addr as usize] = val;
+        Ok(())
+    }
+
+    // return the RPL user flags, so frontends can persist them to disk
+    pub fn get_flags(&self) -> [u8; NUM_FLAGS] {
+        self.flags
+    }
+
+    // restore the RPL user flags, e.g. after reading them back from disk
+    pub fn set_flags(&mut self, flags: [u8; NUM_FLAGS]) {
+        self.flags = flags;
+    }
+
+    /// Returns the XO-CHIP audio pattern, 128 1-bit samples with the most significant bit played first.
+    ///
+    /// The pattern should only be played while the sound timer is running.
+    pub fn get_audio_buffer(&self) -> &[u8; AUDIO_BUFFER_SIZE] {
+        &self.audio_buffer
+    }
+
+    /// Returns the XO-CHIP pitch register.
+    ///
+    /// The pattern's playback rate in Hz is `4000 * 2^((pitch - 64) / 48)`.
+    pub fn get_audio_pitch(&self) -> u8 {
+        self.audio_pitch
+    }
+
+    /// Switches between the classic 4KB address space and the XO-CHIP 64KB one.
+    ///
+    /// This is a machine setting, so it is kept across `reset`.
+    pub fn set_large_memory(&mut self, enabled: bool) {
+        self.large_memory = enabled;
+    }
+
+    /// Selects the interpreter behaviors to emulate, see `Quirks`.
+    ///
+    /// This is a machine setting, so it is kept across `reset`.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        // Switching `accumulate_draws` on starts from the current screen
+        self.sync_visible_screen();
+    }
+
+    // return the interpreter behaviors being emulated
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Sets the CPU speed in instructions per second, `DEFAULT_TARGET_IPS` by default.
+    ///
+    /// The emulator doesn't keep time itself, frontends run `ticks_per_frame` instructions
+    /// for every `tick_timers` call at `TIMER_HZ`. This is a machine setting, so it is kept
+    /// across `reset`.
+    pub fn set_target_ips(&mut self, ips: u32) {
+        self.target_ips = ips;
+    }
+
+    // return the CPU speed in instructions per second
+    pub fn target_ips(&self) -> u32 {
+        self.target_ips
+    }
+
+    /// Moves where ROMs are loaded and where PC starts, 0x200 by default.
+    ///
+    /// A few ROMs, like the ones written for the ETI-660, start at 0x600. This moves PC
+    /// to `addr` right away, but a ROM that is already loaded stays where it is, so call it
+    /// before `load`. This is a machine setting, so it is kept across `reset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::AddressOutOfRange` if `addr` isn't within the 4KB address space.
+    pub fn set_start_address(&mut self, addr: u16) -> Result<(), Chip8Error> {
+        if addr as usize >= RAM_SIZE {
+            return Err(Chip8Error::AddressOutOfRange(addr));
+        }
+
+        self.start_addr = addr;
+        self.pc = addr;
+        Ok(())
+    }
+
+    // return where ROMs are loaded and PC starts
+    pub fn start_address(&self) -> u16 {
+        self.start_addr
+    }
+
+    /// Returns how many instructions to run per frame to reach the target speed.
+    ///
+    /// The result is rounded to the nearest whole number, but is never less than 1.
+    pub fn ticks_per_frame(&self) -> usize {
+        let ticks = self.target_ips.saturating_add(TIMER_HZ / 2) / TIMER_HZ;
+        ticks.max(1) as usize
+    }
+
+    // number of bytes the program can address
+    fn memory_size(&self) -> usize {
+        if self.large_memory { LARGE_RAM_SIZE } else { RAM_SIZE }
+    }
+
+    // wrap an address into the addressable memory, every RAM access goes through this
+    // so malformed ROMs wrap around the end of memory instead of reading past it
+    fn mask_addr(&self, addr: u16) -> u16 {
+        addr & (self.memory_size() - 1) as u16
+    }
+
+    // fail if a ROM of len bytes doesn't fit between the start address and the end of the addressable memory
+    fn check_rom_size(&self, len: usize) -> Result<(), Chip8Error> {
+        if self.start_addr as usize + len > self.memory_size() {
+            return Err(Chip8Error::RomTooLarge(len));
+        }
+
+        Ok(())
+    }
+
+    /// Loads `data` at the start address like `load`, but rejects a ROM that doesn't fit instead of panicking.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::RomTooLarge` and leaves memory untouched if the ROM doesn't fit in memory.
+    pub fn try_load(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        self.check_rom_size(data.len())?;
+        self.load(data);
+        Ok(())
+    }
+
+    /// Reads the ROM at `path` and loads it at the start address, see `load`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from reading the file, or an `InvalidData` error wrapping
+    /// `Chip8Error::RomTooLarge` if the ROM doesn't fit in memory.
+    #[cfg(feature = "std")]
+    pub fn load_path<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        let data = std::fs::read(path)?;
+        self.check_rom_size(data.len())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        self.load(&data);
+        Ok(())
+    }
+
+    /// Decompresses a gzip-compressed ROM, such as a `.ch8.gz` file, and loads it at the start address.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::BadGzip` if `data` isn't valid gzip, or `Chip8Error::RomTooLarge`
+    /// if the decompressed ROM doesn't fit in memory. Memory is left untouched on error.
+    #[cfg(feature = "flate2")]
+    pub fn load_gz(&mut self, data: &[u8]) -> Result<(), Chip8Error> {
+        use std::io::Read;
+
+        // Stop one byte past the largest ROM that fits, so a gzip bomb can't exhaust memory
+        let limit = self.memory_size() - self.start_addr as usize + 1;
+        let mut rom = Vec::new();
+        flate2::read::GzDecoder::new(data)
+            .take(limit as u64)
+            .read_to_end(&mut rom)
+            .map_err(|_| Chip8Error::BadGzip)?;
+
+        self.try_load(&rom)
+    }
+
+    /// Copies `data` into RAM at `addr`, leaving the rest of memory intact, so a program and
+    /// its data can be loaded at different addresses.
+    ///
+    /// Segments may overwrite each other or the ROM, the last one loaded wins. Unlike `load`
+    /// this doesn't change the ROM size used by `opcode_coverage`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::AddressOutOfRange` if `addr` is past the addressable memory, or
+    /// `Chip8Error::RomTooLarge` if `data` runs past its end. Memory is left untouched on error.
+    pub fn load_segment(&mut self, data: &[u8], addr: u16) -> Result<(), Chip8Error> {
+        let start = addr as usize;
+        if start >= self.memory_size() {
+            return Err(Chip8Error::AddressOutOfRange(addr));
+        }
+        if data.len() > self.memory_size() - start {
+            return Err(Chip8Error::RomTooLarge(data.len()));
+        }
+
+        self.ram[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    // load game code from file into our RAM, at the start address
     pub fn load(&mut self, data: &[u8]) {
-        let start = START_ADDR as usize;
-        let end = (START_ADDR as usize) + data.len();
+        let start = self.start_addr as usize;
+        let end = start + data.len();
 
         self.ram[start..end].copy_from_slice(data);
+        self.rom_len = data.len();
+        self.rom_crc = Some(crc32::crc32(data));
+    }
+
+    /// Returns the CRC-32 of the ROM loaded by `load`, a stable identifier for bug reports
+    /// and per-game settings.
+    ///
+    /// The hash is taken when the ROM is loaded, so it doesn't change when the program writes
+    /// over itself. Returns `None` until a ROM is loaded, and again after `reset`.
+    pub fn loaded_rom_hash(&self) -> Option<u32> {
+        self.rom_crc
+    }
+
+    /// Resets the machine like `reset` but keeps the loaded ROM, like the reset button of a console,
+    /// so PC is back at the start address without loading the ROM again.
+    ///
+    /// The ROM's bytes are kept as they are in RAM, including any the program wrote over.
+    /// The rest of memory is cleared and the fonts are reinstalled as usual.
+    pub fn soft_reset(&mut self)
+    {
+        // The start address may have moved since the ROM was loaded, so it can't be trusted
+        // to still fit in memory
+        let start = self.start_addr as usize;
+        let end = (start + self.rom_len).min(self.ram.len());
+        self.ram[..start].fill(0);
+        self.ram[end..].fill(0);
+
+        self.reset_machine();
     }
 
     pub fn reset(&mut self)
     {
-        self.pc = START_ADDR;
-        self.ram = [0; RAM_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.ram = [0; LARGE_RAM_SIZE];
+        self.rom_len = 0;
+        self.rom_crc = None;
+
+        self.reset_machine();
+    }
+
+    // reset everything but memory and the loaded ROM, which are left to the caller
+    fn reset_machine(&mut self)
+    {
+        self.pc = self.start_addr;
+        self.screen = [[0; SCREEN_WORDS]; NUM_PLANES];
+        self.screen_dirty = true;
+        self.intensity = [0; SCREEN_SIZE];
+        self.sync_visible_screen();
         self.v_reg = [0; NUM_REGS];
         self.i_reg = 0;
         self.sp = 0;
-        self.stack = [0; STACK_SIZE];
+        self.stack = [0; MAX_STACK_SIZE];
         self.keys = [false; NUM_KEYS];
         self.dt = 0;
-        self.st = 0;
-        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.update_sound_timer(0);
+        self.hires = false;
+        self.flags = [0; NUM_FLAGS];
+        self.plane_mask = 1;
+        self.audio_buffer = [0; AUDIO_BUFFER_SIZE];
+        self.audio_pitch = DEFAULT_AUDIO_PITCH;
+        self.waiting_for_key = None;
+        self.held_key = None;
+        self.waiting_for_vblank = false;
+        self.halted = false;
+        #[cfg(feature = "alloc")]
+        self.history.clear();
+        #[cfg(feature = "alloc")]
+        self.replay.clear();
+        #[cfg(feature = "alloc")]
+        if let Some(log) = self.input_log.as_mut() {
+            log.events.clear();
+        }
+        self.cycles = 0;
+        self.ticks = 0;
+        self.frames = 0;
+        self.hw_cycles = 0;
+        self.frame_start_hw_cycles = 0;
+        self.load_fonts();
+    }
+
+    // copy FONTSET to RAM at FONTSET_START_ADDR, and BIG_FONTSET right after it
+    fn load_fonts(&mut self)
+    {
+        let start = FONTSET_START_ADDR as usize;
+        self.ram[start..start + FONTSET_SIZE].copy_from_slice(&FONTSET);
+
+        let big_start = BIG_FONTSET_START_ADDR as usize;
+        self.ram[big_start..big_start + BIG_FONTSET_SIZE].copy_from_slice(&BIG_FONTSET);
     }
 
     /// Fetches the operation code (op) from the memory.
@@ -139,200 +1086,577 @@ impl Emu {
     fn fetch(&mut self) -> u16
     {
         // Fetch operation code(2 bytes)
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+        let higher_byte = self.ram[self.mask_addr(self.pc) as usize] as u16;
+        let lower_byte = self.ram[self.mask_addr(self.pc.wrapping_add(1)) as usize] as u16;
 
         // Add 8 zero bytes after the higher byte, and use OR to combine with the lower byte
         // It will get a complete instruction
         let op = (higher_byte << 8) | lower_byte;
 
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
 
         op
     }
 
     /// Executes a single instruction in the game.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instruction fails, use `try_tick` to handle the error instead.
     pub fn tick(&mut self)
     {
+        if let Err(err) = self.try_tick() {
+            panic!("Failed to execute instruction: {}", err);
+        }
+    }
+
+    /// Executes a single instruction in the game, returning an error if the ROM misbehaves.
+    ///
+    /// Does nothing while `waiting_for_vblank` is true, while `waiting_for_key` until a key
+    /// is pressed and released, or once the program has `halted`.
+    pub fn try_tick(&mut self) -> Result<(), Chip8Error>
+    {
+        // Replayed key presses land between the same ticks they were recorded at
+        #[cfg(feature = "alloc")]
+        self.apply_replay();
+        self.ticks += 1;
+
+        // A DRAW with the display_wait quirk holds the CPU until the next frame
+        if self.waiting_for_vblank || self.halted {
+            return Ok(());
+        }
+
+        // WAIT KEY holds the CPU too, until the key it waits for is released
+        if self.waiting_for_key.is_some() && !self.poll_wait_key() {
+            return Ok(());
+        }
+
+        // Remember the state before this instruction, so it can be stepped back
+        #[cfg(feature = "alloc")]
+        self.record_history();
+
         // Fetch value from game at the memory address stored in PC, and load into RAM
+        let addr = self.pc;
         let op = self.fetch();
 
+        // Report the instruction before it changes any state
+        #[cfg(feature = "alloc")]
+        if let Some(hook) = self.trace_hook.as_mut() {
+            hook(addr, op);
+        }
+
         // Decode instruction
         // Execute
         // Move PC to next instruction
-        self.execute(op);
+        self.execute(addr, op)?;
+
+        self.cycles += 1;
+        self.add_cycle_cost(op);
+
+        Ok(())
+    }
+
+    /// Executes up to `n` instructions, stopping at the first one that fails or once the program halts.
+    ///
+    /// Returns the number of instructions executed successfully, which is less than `n`
+    /// when the ROM misbehaved or exited. Use `try_tick` to find out what went wrong.
+    pub fn run_cycles(&mut self, n: usize) -> usize
+    {
+        for executed in 0..n {
+            if self.halted || self.try_tick().is_err() {
+                return executed;
+            }
+        }
+
+        n
+    }
+
+    /// Emulates a whole frame, `ticks_per_frame` instructions followed by `tick_timers`.
+    ///
+    /// The frame ends early when a DRAW waits for the vertical blank (the `display_wait` quirk).
+    /// On error the timers aren't ticked.
+    pub fn run_frame(&mut self, ticks_per_frame: usize) -> Result<(), Chip8Error>
+    {
+        for _ in 0..ticks_per_frame {
+            self.try_tick()?;
+
+            if self.waiting_for_vblank {
+                break;
+            }
+        }
+
+        self.tick_timers();
+
+        Ok(())
+    }
+
+    /// Emulates a frame like `run_frame`, then sleeps for whatever is left of `target_frame`
+    /// since `frame_start`, so frontends don't each have to pace the emulator themselves.
+    ///
+    /// This is optional, frontends synced to a display or an event loop can keep calling
+    /// `run_frame`. Nothing sleeps when the frame is already behind schedule, so a slow
+    /// host runs as fast as it can rather than falling further behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from `run_frame` without sleeping.
+    #[cfg(feature = "std")]
+    pub fn run_frame_throttled(
+        &mut self,
+        ticks_per_frame: usize,
+        frame_start: std::time::Instant,
+        target_frame: std::time::Duration,
+    ) -> Result<(), Chip8Error>
+    {
+        self.run_frame(ticks_per_frame)?;
+
+        if let Some(remaining) = target_frame.checked_sub(frame_start.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+
+        Ok(())
     }
 
     /// Tick the timers to update their values.
+    ///
+    /// This is the vertical blank, so it also releases a DRAW waiting for it.
     pub fn tick_timers(&mut self)
     {
+        self.frames += 1;
+        self.end_frame_cycles();
+        self.waiting_for_vblank = false;
+
+        if self.phosphor_decay > 0 {
+            self.refresh_intensity(self.phosphor_decay);
+        }
+
+        if self.quirks.accumulate_draws {
+            self.composite_frame();
+        }
+
         if self.dt > 0 {
             self.dt -= 1
         }
 
         if self.st > 0 {
-            if self.st == 1 {
-                // 'BEEP' noise
+            // 'BEEP' noise stops once the timer reaches zero
+            self.update_sound_timer(self.st - 1);
+        }
+    }
+
+    /// Returns true while the sound timer is running, which is when the beep should be heard.
+    pub fn is_beeping(&self) -> bool {
+        self.st > 0
+    }
+
+    /// Installs a callback told about every change of `is_beeping`.
+    ///
+    /// It fires with true on the rising edge, when FX18 loads a non-zero value into an idle
+    /// sound timer, and with false on the falling edge, when the timer reaches zero
+    /// (counting down, FX18 with 0, or `reset`). Reloading a running timer doesn't fire it.
+    #[cfg(feature = "alloc")]
+    pub fn set_beep_callback(&mut self, callback: BeepCallback) {
+        self.beep_callback = Some(callback);
+    }
+
+    // set the sound timer, notifying the beep callback when the beep starts or stops
+    fn update_sound_timer(&mut self, st: u8)
+    {
+        #[cfg(feature = "alloc")]
+        let was_beeping = self.is_beeping();
+        self.st = st;
+
+        #[cfg(feature = "alloc")]
+        if was_beeping != self.is_beeping() {
+            if let Some(callback) = self.beep_callback.as_mut() {
+                callback(self.st > 0);
+            }
+        }
+    }
+
+    /// Returns true when a DRAW executed with the `display_wait` quirk is waiting for the next frame.
+    ///
+    /// Until `tick_timers` is called, `tick` does nothing, so frontends running a fixed number
+    /// of ticks per frame should stop early once this returns true.
+    pub fn waiting_for_vblank(&self) -> bool {
+        self.waiting_for_vblank
+    }
+
+    /// Returns the register a WAIT KEY (FX0A) will store the key in, while it waits for a key
+    /// to be pressed and released.
+    ///
+    /// Frontends can show a prompt while this is `Some`. `tick` does nothing meanwhile,
+    /// but the timers keep running.
+    pub fn waiting_for_key(&self) -> Option<usize> {
+        self.waiting_for_key
+    }
+
+    // remember the first key pressed during WAIT KEY, and return whether it was released since
+    fn poll_wait_key(&mut self) -> bool {
+        if self.held_key.is_none() {
+            self.held_key = self.keys.iter().position(|&pressed| pressed);
+        }
+
+        matches!(self.held_key, Some(key) if !self.keys[key])
+    }
+
+    /// Returns true once the program executed 00FD (EXIT), `tick` does nothing from then on.
+    ///
+    /// The timers keep running. `reset` clears it.
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    // whether the XO-CHIP bitplane is selected for drawing
+    fn plane_selected(&self, plane: usize) -> bool {
+        self.plane_mask & (1 << plane) != 0
+    }
+
+    /// Clears every selected bitplane.
+    fn clear_screen(&mut self)
+    {
+        for plane in 0..NUM_PLANES {
+            if self.plane_selected(plane) {
+                self.screen[plane] = [0; SCREEN_WORDS];
+            }
+        }
+        self.screen_dirty = true;
+    }
+
+    /// Scrolls the selected planes down by `rows` pixels, vacated rows are switched off.
+    fn scroll_down(&mut self, rows: usize)
+    {
+        let height = self.screen_height();
+
+        for plane in 0..NUM_PLANES {
+            if !self.plane_selected(plane) {
+                continue;
+            }
+
+            // Walk from the bottom up so every source row is read before it gets overwritten
+            for y in (0..height).rev() {
+                let row = if y >= rows { self.row(plane, y - rows) } else { 0 };
+                self.set_row(plane, y, row);
+            }
+        }
+    }
+
+    /// Scrolls the selected planes right by `cols` pixels, vacated columns are switched off.
+    fn scroll_right(&mut self, cols: usize)
+    {
+        let height = self.screen_height();
+
+        for plane in 0..NUM_PLANES {
+            if !self.plane_selected(plane) {
+                continue;
+            }
+
+            // The leftmost pixel is the most significant bit, pixels past the right edge are dropped by set_row
+            for y in 0..height {
+                let row = self.row(plane, y) >> cols;
+                self.set_row(plane, y, row);
+            }
+        }
+    }
+
+    /// Scrolls the selected planes left by `cols` pixels, vacated columns are switched off.
+    fn scroll_left(&mut self, cols: usize)
+    {
+        let height = self.screen_height();
+
+        for plane in 0..NUM_PLANES {
+            if !self.plane_selected(plane) {
+                continue;
+            }
+
+            for y in 0..height {
+                let row = self.row(plane, y) << cols;
+                self.set_row(plane, y, row);
             }
-            self.st -= 1;
         }
     }
 
+    /// XORs a sprite stored at `i_reg` onto every selected plane.
+    ///
+    /// The sprite is `num_cols` pixels wide (8 or 16) and `num_rows` pixels high.
+    /// When more than one plane is selected, the data for the next plane follows
+    /// straight after the previous plane's sprite, as defined by XO-CHIP.
+    ///
+    /// The starting coordinate wraps around the screen unless the `wrap_sprite_origin` quirk
+    /// is off. Pixels past the right or bottom edge wrap too, unless the `clip_sprite_body`
+    /// quirk is set, then they are dropped.
+    ///
+    /// Returns true if any lit pixel got switched off (collision).
+    fn draw_sprite(&mut self, x_coord: u16, y_coord: u16, num_rows: u16, num_cols: u16) -> bool
+    {
+        // Hold the CPU until the vertical blank, as the COSMAC VIP does
+        if self.quirks.display_wait {
+            self.waiting_for_vblank = true;
+        }
+
+        let width = self.screen_width();
+        let height = self.screen_height();
+        let clip = self.quirks.clip_sprite_body;
+
+        // A clipped sprite whose origin doesn't wrap is entirely off screen. When the body wraps,
+        // wrapping the origin lands every pixel in the same place, so it makes no difference
+        let off_screen = x_coord as usize >= width || y_coord as usize >= height;
+        if off_screen && clip && !self.quirks.wrap_sprite_origin {
+            return false;
+        }
+
+        // Wrap against the active resolution
+        let x_coord = x_coord as usize % width;
+        let y_coord = y_coord as usize % height;
+
+        let bytes_per_row = num_cols / 8;
+        let mut addr = self.i_reg;
+
+        // Keep track if any pixels were flipped
+        let mut flipped = false;
+
+        for plane in 0..NUM_PLANES {
+            if !self.plane_selected(plane) {
+                continue;
+            }
+
+            // Iterate over each row of our sprite
+            for y_line in 0..num_rows {
+
+                // Determine which memory address our row's data is stored
+                let row_addr = addr.wrapping_add(y_line * bytes_per_row);
+
+                // Gather the row's pixels, the leftmost one in the most significant bit
+                let mut pixels = 0u128;
+                for byte in 0..bytes_per_row {
+                    let pixel_addr = self.mask_addr(row_addr.wrapping_add(byte));
+                    pixels = (pixels << 8) | self.ram[pixel_addr as usize] as u128;
+                }
+                let sprite = pixels << (128 - num_cols as u32);
+
+                let mut y = y_coord + y_line as usize;
+
+                // Clipped sprites stop at the edge
+                if y >= height {
+                    if clip {
+                        continue;
+                    }
+
+                    // Otherwise sprites wrap around screen. so apply modulo
+                    y %= height;
+                }
+
+                // Move the sprite to x_coord, pixels past the right edge are dropped when clipping
+                // and wrap to the left edge otherwise
+                let line = if clip {
+                    sprite >> x_coord
+                } else if self.hires {
+                    sprite.rotate_right(x_coord as u32)
+                } else {
+                    ((((sprite >> 64) as u64).rotate_right(x_coord as u32)) as u128) << 64
+                };
+
+                // Check if we're about to flip any lit pixel and set
+                let row = self.row(plane, y);
+                flipped |= row & line != 0;
+                self.set_row(plane, y, row ^ line);
+            }
+
+            // The next plane's sprite data follows this one
+            addr = addr.wrapping_add(num_rows * bytes_per_row);
+        }
+
+        flipped
+    }
 
     /// Executes an operation based on the given opcode.
     ///
-    /// The opcode is a 16-bit unsigned integer. It is split into four parts: digit1, digit2, digit3, and digit4.
-    /// Each digit represents a specific part of the operation.
+    /// The opcode is a 16-bit unsigned integer. It is decoded into an `Instruction` first,
+    /// and the decoded instruction is then applied to the CPU state.
     ///
     /// # Arguments
     ///
+    /// * `addr` - The address `op` was fetched from, reported in errors.
     /// * `op` - The opcode to execute.
-    fn execute(&mut self, op: u16)
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::StackOverflow` or `Chip8Error::StackUnderflow` when CALL or RET misuse the stack,
+    /// and `Chip8Error::UnknownOpcode` when the opcode is not implemented.
+    fn execute(&mut self, addr: u16, op: u16) -> Result<(), Chip8Error>
     {
-        // Split operation code to four parts
-        let digit1 = (op & 0xF000) >> 12;
-        let digit2 = (op & 0x0F00) >> 8;
-        let digit3 = (op & 0x00F0) >> 4;
-        let digit4 = op & 0x000F;
+        match decode(op) {
+            // I = NNNN (XO-CHIP)
+            Instruction::LoadLongIndex => {
+                // The 16 bit address is stored in the 2 bytes following the instruction
+                let higher_byte = self.ram[self.mask_addr(self.pc) as usize] as u16;
+                let lower_byte = self.ram[self.mask_addr(self.pc.wrapping_add(1)) as usize] as u16;
+                self.i_reg = (higher_byte << 8) | lower_byte;
 
-        match (digit1, digit2, digit3, digit4) {
+                // Skip over the address
+                self.pc = self.pc.wrapping_add(2);
+            },
+            // AUDIO (XO-CHIP)
+            Instruction::LoadAudio => {
+                // Copy the 16 byte pattern stored at I into the audio buffer
+                for idx in 0..AUDIO_BUFFER_SIZE {
+                    let addr = self.mask_addr(self.i_reg.wrapping_add(idx as u16));
+                    self.audio_buffer[idx] = self.ram[addr as usize];
+                }
+            },
+            // PLANE X (XO-CHIP)
+            Instruction::SelectPlanes(mask) => {
+                // X is a bitmask of the planes to draw on
+                self.plane_mask = mask & 0b11;
+            },
+            // LOAD V0 to VX from RPL flags (SUPER-CHIP)
+            Instruction::LoadFlags { x } => {
+                // There are only 8 flag registers, so VX stops at V7
+                let x = (x as usize).min(NUM_FLAGS - 1);
+                self.v_reg[..=x].copy_from_slice(&self.flags[..=x]);
+            },
+            // STORE V0 to VX into RPL flags (SUPER-CHIP)
+            Instruction::StoreFlags { x } => {
+                let x = (x as usize).min(NUM_FLAGS - 1);
+                self.flags[..=x].copy_from_slice(&self.v_reg[..=x]);
+            },
             // LOAD V0 to VX
-            (0xF, _, 6, 5) => {
-                let x = digit2 as usize;
-                let i = self.i_reg as usize;
+            Instruction::LoadRegs { x } => {
+                let x = x as usize;
                 for idx in 0..=x {
-                    self.v_reg[idx] = self.ram[i + idx];
+                    // Wrap around the end of memory rather than reading past it
+                    let addr = self.mask_addr(self.i_reg.wrapping_add(idx as u16));
+                    self.v_reg[idx] = self.ram[addr as usize];
                 }
             },
             // STORE V0 to VX
-            (0xF, _, 5, 5) => {
-                let x = digit2 as usize;
-                let i = self.i_reg as usize;
+            Instruction::StoreRegs { x } => {
+                let x = x as usize;
                 for idx in 0..=x {
-                    self.ram[i + idx] = self.v_reg[idx];
+                    // Wrap around the end of memory rather than writing past it
+                    let addr = self.mask_addr(self.i_reg.wrapping_add(idx as u16));
+                    self.ram[addr as usize] = self.v_reg[idx];
                 }
             },
             // BCD(Binary convert to Decimal)
-            (0xF, _, 3, 3) => {
-                let x = digit2 as usize;
-                let vx = self.v_reg[x] as f32;
-
-                // Fetch the hundreds digit by dividing by 100 and tossing the decimal
-                let hundreds = (vx / 100.0).floor() as u8;
-                // Fetch the tens digit by dividing by 10, tossing the ones digit and the decimal
-                let tens = ((vx / 10.0) % 10.0).floor() as u8;
+            Instruction::Bcd { x } => {
+                let x = x as usize;
+                let vx = self.v_reg[x];
+
+                // Fetch the hundreds digit, integer division tosses the rest
+                let hundreds = vx / 100;
+                // Fetch the tens digit by dividing by 10 and tossing the hundreds
+                let tens = (vx / 10) % 10;
                 // Fetch the one digit by tossing the hundreds and the tens
-                let ones = (vx % 10.0).floor() as u8;
+                let ones = vx % 10;
 
-                self.ram[self.i_reg as usize] = hundreds;
-                self.ram[(self.i_reg + 1) as usize] = tens;
-                self.ram[(self.i_reg + 2) as usize] = ones;
+                for (offset, digit) in [hundreds, tens, ones].into_iter().enumerate() {
+                    let addr = self.mask_addr(self.i_reg.wrapping_add(offset as u16));
+                    self.ram[addr as usize] = digit;
+                }
+            },
+            // PITCH = VX (XO-CHIP)
+            Instruction::SetPitch { x } => {
+                let x = x as usize;
+                self.audio_pitch = self.v_reg[x];
+            },
+            // I = BIG FONT (SUPER-CHIP)
+            Instruction::BigFontChar { x } => {
+                let x = x as usize;
+                let c = self.v_reg[x] as u16;
+                // big character's size is 10 * RAM address
+                self.i_reg = BIG_FONTSET_START_ADDR + c * 10
             },
             // I = FONT
-            (0xF, _, 2, 9) => {
-                let x = digit2 as usize;
+            Instruction::FontChar { x } => {
+                let x = x as usize;
                 let c = self.v_reg[x] as u16;
-                // character's size is 5 * RAM address
-                self.i_reg = c * 5
+                // every character is 5 bytes long
+                self.i_reg = FONTSET_START_ADDR + c * 5
             },
             // I += VX
-            (0xF, _, 1, 0xE) => {
-                let x = digit2 as usize;
+            Instruction::AddIndex { x } => {
+                let x = x as usize;
                 let vx = self.v_reg[x] as u16;
-                self.i_reg = self.i_reg.wrapping_add(vx);
+                // I is 12 bits wide, unless the 64KB address space is enabled
+                self.i_reg = self.mask_addr(self.i_reg.wrapping_add(vx));
             },
             // ST = VX
-            (0xF, _, 1, 8) => {
-                let x = digit2 as usize;
-                self.st = self.v_reg[x];
+            Instruction::SetSound { x } => {
+                let x = x as usize;
+                self.update_sound_timer(self.v_reg[x]);
             },
             // DT = VX
-            (0xF, _, 1, 5) => {
-                let x = digit2 as usize;
+            Instruction::SetDelay { x } => {
+                let x = x as usize;
                 self.dt = self.v_reg[x];
             },
             // WAIT KEY
-            (0xF, _, 0, 0xA) => {
-                let x = digit2 as usize;
-                let mut pressed = false;
-
-                // check v registers
-                // if any key is pressed and that register will be true
-                // the program will be continuously
-                for i in 0..self.keys.len() {
-                    if self.keys[i] {
-                        self.v_reg[x] = i as u8;
-                        pressed = true;
-                        break;
-                    }
-                }
+            Instruction::WaitKey { x } => {
+                let x = x as usize;
 
-                // if not pressed, redo operation code again until pressed
-                if !pressed {
-                    self.pc -= 2;
+                if self.poll_wait_key() {
+                    // the key has been released, so the program will be continuously
+                    self.v_reg[x] = self.held_key.take().unwrap() as u8;
+                    self.waiting_for_key = None;
+                } else {
+                    // the first pressed key is remembered, other keys are ignored until it is released
+                    // PC stays on this instruction, so `tick` runs it again once the wait is over
+                    self.waiting_for_key = Some(x);
+                    self.pc = self.pc.wrapping_sub(2);
                 }
             },
             // VX = DT
-            (0xF, _, 0, 7) => {
-                let x = digit2 as usize;
+            Instruction::GetDelay { x } => {
+                let x = x as usize;
                 self.v_reg[x] = self.dt;
             },
             // SKIP KEY RELEASE
-            (0xE, _, 0xA, 1) => {
-                let x = digit2 as usize;
-                let vx = self.v_reg[x];
+            Instruction::SkipKeyReleased { x } => {
+                let x = x as usize;
+                // Only the low nibble selects a key, as there are just 16 of them
+                let vx = self.v_reg[x] & 0x0F;
                 let key = self.keys[vx as usize];
                 if !key { 
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             },
             // SKIP KEY PRESS
-            (0xE, _, 9, 0xE) => {
-                let x = digit2 as usize;
-                let vx = self.v_reg[x];
+            Instruction::SkipKeyPressed { x } => {
+                let x = x as usize;
+                // Only the low nibble selects a key, as there are just 16 of them
+                let vx = self.v_reg[x] & 0x0F;
                 let key = self.keys[vx as usize];
                 if key {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
+                }
+            },
+            // DRAW 16x16 (SUPER-CHIP)
+            Instruction::Draw { x, y, n: 0 } => {
+                // Get the (x, y) coords for our sprite
+                let x_coord = self.v_reg[x as usize] as u16;
+                let y_coord = self.v_reg[y as usize] as u16;
+
+                // A large sprite is 16 rows of 2 bytes each
+                let flipped = self.draw_sprite(x_coord, y_coord, 16, 16);
+
+                // Populate CF register
+                if flipped {
+                    self.v_reg[0xF] = 1;
+                } else {
+                    self.v_reg[0xF] = 0;
                 }
             },
             // DRAW
-            (0xD, _, _, _) => {
+            Instruction::Draw { x, y, n } => {
                 // Get the (x, y) coords for our sprite
-                let x_coord = self.v_reg[digit2 as usize] as u16;
-                let y_coord = self.v_reg[digit3 as usize] as u16;
+                let x_coord = self.v_reg[x as usize] as u16;
+                let y_coord = self.v_reg[y as usize] as u16;
 
                 // The lst digital determines how many rows high our sprite is
-                let num_rows = digit4;
-
-                // Keep track if any pixels were flipped
-                let mut flipped = false;
-
-                // Iterate over each row of our sprite
-                for y_line in 0..num_rows {
-
-                    // Determine which memory address our row's data is stored
-                    let addr = self.i_reg + y_line;
-                    let pixels = self.ram[addr as usize];
-
-                    // Iterate over each column in our row
-                    for x_line in 0..8
-                    {
-                        // User a mask to fetch current pixel's bit. Only flip if a 1
-                        if (pixels & (0b1000_0000 >> x_line)) != 0
-                        {
-                            // Sprites should wrap around screen. so apply modulo
-                            let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                            let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
-
-                            // Get our pixel's index for our 1D screen array
-                            let idx = x + SCREEN_WIDTH * y;
-
-                            // Check if we're about to flip the pixel and set
-                            flipped |= self.screen[idx];
-                            self.screen[idx] ^= true;
-                        }
-                    }
-                }
+                let num_rows = n as u16;
+
+                let flipped = self.draw_sprite(x_coord, y_coord, num_rows, 8);
 
                 // Populate CF register
                 if flipped {
@@ -342,33 +1666,30 @@ impl Emu {
                 }
             },
             // VX = rand() & NN
-            (0xC, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
-                let rng: u8 = random();
+            Instruction::Random { x, nn } => {
+                let x = x as usize;
+                let rng: u8 = self.rng.gen();
                 self.v_reg[x] = rng & nn;
             },
             // JMP V0 + NNN
-            (0xB, _, _, _) => {
-                let nnn = op & 0xFFF;
+            Instruction::JumpOffset(nnn) => {
                 self.pc = (self.v_reg[0] as u16) + nnn;
             },
             // I = NNN
-            (0xA, _, _, _) => {
-                let nnn = op & 0xFFF;
+            Instruction::SetIndex(nnn) => {
                 self.i_reg = nnn;
             },
             // SKIP VX != VY
-            (9, _, _, 0) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::SkipNeReg { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
                 if self.v_reg[x] != self.v_reg[y] {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             },
             // VX <<= 1
-            (8, _, _, 0xE) => {
-                let x = digit2 as usize;
+            Instruction::ShiftLeft { x, .. } => {
+                let x = x as usize;
 
                 // The variable `msb` stands for "most significant bit", which is the highest bit in a series of numbers in binary notation.
                 // In this context, it's the highest bit in the actual byte of the value in `self.v_reg[x]`.
@@ -381,9 +1702,9 @@ impl Emu {
                 self.v_reg[0xF] = msb;
             },
             // VX = VY - VX
-            (8, _, _, 7) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::SubN { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (new_vx, borrow) = self.v_reg[y].overflowing_sub(self.v_reg[x]);
                 let new_vf = if borrow { 0 } else { 1 };
@@ -392,8 +1713,8 @@ impl Emu {
                 self.v_reg[0xF] = new_vf;
             },
             // VX >>= 1
-            (8, _, _, 6) => {
-                let x = digit2 as usize;
+            Instruction::ShiftRight { x, .. } => {
+                let x = x as usize;
 
                 // The variable `lsb` is short for "Least Significant Bit".
                 // In the context of binary numbers, the least significant bit is the bit position in a binary integer giving the
@@ -405,9 +1726,9 @@ impl Emu {
                 self.v_reg[0xF] = lsb
             },
             // VX -= VY
-            (8, _, _, 5) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::SubReg { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (new_vx, borrow) = self.v_reg[x].overflowing_sub(self.v_reg[y]);
                 let new_vf = if borrow { 0 } else { 1 };
@@ -416,9 +1737,9 @@ impl Emu {
                 self.v_reg[0xF] = new_vf;
             },
             // VX += VY
-            (8, _, _, 4) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::AddReg { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
 
                 let (new_vx, carry) = self.v_reg[x].overflowing_add(self.v_reg[y]);
                 let new_vy = if carry { 1 } else { 0 };
@@ -429,93 +1750,128 @@ impl Emu {
                 // This is because CHIP-8, the architecture that our emulator is mimicking, uses a total of 16 registers (from V0 to VF).
                 // The last register, VF (which corresponds to `v_reg[0xF]` in our case), is used as the carry flag in arithmetic operations.
                 // This special register (VF) stores the overflow bit resulting from arithmetic operations, acting as a flag for the next instruction if needed.
-                // In this specific instruction `(8, _, _, 4) => {}`, if the addition of Vx and Vy results in an overflow, the VF register is set to 1. Otherwise, it is set to 0.
+                // In this specific instruction `Instruction::AddReg`, if the addition of Vx and Vy results in an overflow, the VF register is set to 1. Otherwise, it is set to 0.
                 self.v_reg[0xF] = new_vy;
             },
             // VX ^= VY
-            (8, _, _, 3) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::Xor { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
                 self.v_reg[x] ^= self.v_reg[y];
             },
             // VX &= VY
-            (8, _, _, 2) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::And { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
                 self.v_reg[x] &= self.v_reg[y];
             },
             // VX |= VY
-            (8, _, _, 1) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::Or { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
                 self.v_reg[x] |= self.v_reg[y];
             },
             // VX = VY
-            (8, _, _, 0) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::Move { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
                 self.v_reg[x] = self.v_reg[y];
             },
             // VX += NN
-            (7, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
+            Instruction::AddImm { x, nn } => {
+                let x = x as usize;
                 self.v_reg[x] = self.v_reg[x].wrapping_add(nn);
             },
             // VX = NN
-            (6, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
+            Instruction::SetReg { x, nn } => {
+                let x = x as usize;
                 self.v_reg[x] = nn;
             },
             // SKIP VX == VY
-            (5, _, _, 0) => {
-                let x = digit2 as usize;
-                let y = digit3 as usize;
+            Instruction::SkipEqReg { x, y } => {
+                let x = x as usize;
+                let y = y as usize;
                 if self.v_reg[x] == self.v_reg[y] {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             },
             // SKIP VX != NN
-            (4, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
+            Instruction::SkipNeImm { x, nn } => {
+                let x = x as usize;
                 if self.v_reg[x] != nn {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             },
             // SKIP VX == NN
-            (3, _, _, _) => {
-                let x = digit2 as usize;
-                let nn = (op & 0xFF) as u8;
+            Instruction::SkipEqImm { x, nn } => {
+                let x = x as usize;
                 if self.v_reg[x] == nn {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             },
             // CALL NNN
-            (2, _, _, _) => {
-                let nnn = op & 0xFFF;
-                self.push(self.pc);
-                self.pc = nnn;
+            Instruction::Call(nnn) => {
+                // Nothing is pushed when the stack is full and the overflow policy ignores it
+                if self.push(self.pc)? {
+                    self.pc = nnn;
+                }
             },
             // JMP NNN
-            (1, _, _, _) => {
+            Instruction::Jump(nnn) => {
                 // move PC to given address
-                let nnn = op & 0xFFF;
                 self.pc = nnn;
             },
             // RET
-            (0, 0, 0xE, 0xE) => {
-                let ret_addr = self.pop();
+            Instruction::Return => {
+                let ret_addr = self.pop()?;
                 self.pc = ret_addr;
             },
+            // HIGH (switch to 128x64)
+            Instruction::HighRes => {
+                self.hires = true;
+                self.screen = [[0; SCREEN_WORDS]; NUM_PLANES];
+                self.screen_dirty = true;
+                // The pixel layout changes with the resolution
+                self.intensity = [0; SCREEN_SIZE];
+                self.sync_visible_screen();
+            },
+            // EXIT (SUPER-CHIP)
+            Instruction::Exit => {
+                self.halted = true;
+            },
+            // LOW (switch to 64x32)
+            Instruction::LowRes => {
+                self.hires = false;
+                self.screen = [[0; SCREEN_WORDS]; NUM_PLANES];
+                self.screen_dirty = true;
+                // The pixel layout changes with the resolution
+                self.intensity = [0; SCREEN_SIZE];
+                self.sync_visible_screen();
+            },
+            // SCROLL LEFT 4
+            Instruction::ScrollLeft => {
+                // In lores the scroll amounts are halved, like on the original SUPER-CHIP
+                let cols = if self.hires { 4 } else { 2 };
+                self.scroll_left(cols);
+            },
+            // SCROLL RIGHT 4
+            Instruction::ScrollRight => {
+                let cols = if self.hires { 4 } else { 2 };
+                self.scroll_right(cols);
+            },
+            // SCROLL DOWN N
+            Instruction::ScrollDown(n) => {
+                let n = n as usize;
+                let rows = if self.hires { n } else { n / 2 };
+                self.scroll_down(rows);
+            },
             // CLS
-            (0, 0, 0xE, 0) => { self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT] },
+            Instruction::ClearScreen => self.clear_screen(),
             // NOP
-            (0, 0, 0, 0) => return,
-            (_, _, _, _) => {
-                unimplemented!("Unimplemented opcode:{}", op)
-            }
+            Instruction::Nop => (),
+            Instruction::Unknown(op) => return Err(Chip8Error::UnknownOpcode { addr, op }),
         }
+
+        Ok(())
     }
 }
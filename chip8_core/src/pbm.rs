@@ -0,0 +1,25 @@
+use crate::Emu;
+
+impl Emu {
+    /// Writes the first plane to `path` as a binary PBM image, sized to the active resolution.
+    ///
+    /// Lit pixels are 1s, which PBM viewers show black on white. The output only depends on
+    /// the screen, so it can be committed as a golden image and compared byte for byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from creating or writing the file.
+    pub fn write_screen_pbm<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()>
+    {
+        let (width, height) = self.screen_dimensions();
+
+        // A row is 64 or 128 pixels, so rows are whole words with the leftmost pixel first,
+        // just like PBM packs them
+        let mut pbm = format!("P4\n{} {}\n", width, height).into_bytes();
+        for word in self.get_display_bits() {
+            pbm.extend_from_slice(&word.to_be_bytes());
+        }
+
+        std::fs::write(path, pbm)
+    }
+}
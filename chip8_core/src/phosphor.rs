@@ -0,0 +1,69 @@
+use crate::{Emu, SCREEN_SIZE};
+
+// Intensity of a lit pixel
+const FULL_INTENSITY: u8 = u8::MAX;
+
+impl Emu {
+    /// Turns on phosphor persistence: lit pixels fade out over a few frames instead of
+    /// vanishing at once, which hides most of the flicker of XOR drawing.
+    ///
+    /// Every `tick_timers` an unlit pixel loses `decay` out of 255, so 64 fades a pixel out
+    /// in 4 frames. 0, the default, turns it off and `get_display_intensity` is no longer
+    /// updated. This is a display setting, so it is kept across `reset`.
+    pub fn set_phosphor_decay(&mut self, decay: u8)
+    {
+        self.phosphor_decay = decay;
+
+        // Start from the current screen rather than from whatever was left over
+        self.intensity = [0; SCREEN_SIZE];
+        self.refresh_intensity(0);
+    }
+
+    // return how much intensity an unlit pixel loses per frame, 0 when disabled
+    pub fn phosphor_decay(&self) -> u8
+    {
+        self.phosphor_decay
+    }
+
+    /// Returns one intensity per pixel of the first plane, row by row from the top left corner,
+    /// sized to the active resolution.
+    ///
+    /// Lit pixels are 255 and unlit ones count down towards 0 by the decay every frame, see
+    /// `set_phosphor_decay`. Frontends can map it to gray levels instead of using `get_display`.
+    pub fn get_display_intensity(&self) -> &[u8]
+    {
+        &self.intensity[..self.screen_width() * self.screen_height()]
+    }
+
+    // light the pixels of row y that a DRAW or a scroll just turned on, so a pixel that is
+    // only lit between two frames still glows
+    pub(crate) fn light_row(&mut self, y: usize, old: u128, new: u128)
+    {
+        let width = self.screen_width();
+        let mut turned_on = new & !old;
+
+        while turned_on != 0 {
+            let x = turned_on.leading_zeros() as usize;
+            self.intensity[y * width + x] = FULL_INTENSITY;
+            turned_on &= !(1 << (127 - x));
+        }
+    }
+
+    // once per frame: lit pixels are at full intensity, the others fade by `decay`
+    pub(crate) fn refresh_intensity(&mut self, decay: u8)
+    {
+        let width = self.screen_width();
+
+        for y in 0..self.screen_height() {
+            let row = self.row(0, y);
+            for x in 0..width {
+                let pixel = &mut self.intensity[y * width + x];
+                if row & (1 << (127 - x)) != 0 {
+                    *pixel = FULL_INTENSITY;
+                } else {
+                    *pixel = pixel.saturating_sub(decay);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::crc32::crc32;
+
+/// Quirks selects between behaviors that differ across CHIP-8 interpreters.
+///
+/// The defaults, `Quirks::DEFAULT`, match the original behavior of this emulator: every quirk
+/// is off except `wrap_sprite_origin`. Quirks are a machine setting, so they are kept across `reset`.
+///
+/// - `wrap_sprite_origin`: DXYN wraps a starting coordinate past the right or bottom edge back
+///   onto the screen. Without it, such a sprite is drawn off screen, so with `clip_sprite_body`
+///   nothing of it shows. On by default.
+/// - `clip_sprite_body`: DXYN clips the pixels of a sprite past the right and bottom edges
+///   instead of wrapping them around, as SUPER-CHIP does.
+/// - `display_wait`: DXYN waits for the vertical blank like the COSMAC VIP, so at most one
+///   sprite is drawn per frame. See `Emu::waiting_for_vblank`.
+/// - `accumulate_draws`: the display only changes once per frame, and shows every pixel lit
+///   during it, so sprites erased and redrawn between two frames don't flicker. DRAW still
+///   collides with the real screen, so VF is unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    pub wrap_sprite_origin: bool,
+    pub clip_sprite_body: bool,
+    pub display_wait: bool,
+    pub accumulate_draws: bool,
+}
+
+impl Quirks {
+    /// The default quirks, usable in constants where `Default::default` isn't.
+    pub const DEFAULT: Quirks = Quirks {
+        wrap_sprite_origin: true,
+        clip_sprite_body: false,
+        display_wait: false,
+        accumulate_draws: false,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::DEFAULT
+    }
+}
+
+// CRC-32 of known ROMs and the quirks they need, the ROMs are the ones bundled in web/roms
+const KNOWN_ROMS: &[(u32, Quirks)] = &[
+    // BLITZ draws the buildings past the bottom edge and expects them to be cut off
+    (0xD106_C808, Quirks { clip_sprite_body: true, ..Quirks::DEFAULT }),
+    // BRIX
+    (0xAAA4_4D0B, Quirks::DEFAULT),
+    // INVADERS
+    (0xEAD6_25B8, Quirks::DEFAULT),
+    // PONG
+    (0x7D75_A857, Quirks::DEFAULT),
+    // TETRIS
+    (0x0CE7_0772, Quirks::DEFAULT),
+    // UFO
+    (0x3314_13E7, Quirks::DEFAULT),
+];
+
+/// Looks up the quirks known to suit `rom`, by the CRC-32 of its contents.
+///
+/// Returns `None` for ROMs that aren't in the built-in table, frontends should keep
+/// their configured quirks in that case.
+pub fn detect_quirks(rom: &[u8]) -> Option<Quirks> {
+    let crc = crc32(rom);
+
+    KNOWN_ROMS
+        .iter()
+        .find(|&&(known, _)| known == crc)
+        .map(|&(_, quirks)| quirks)
+}
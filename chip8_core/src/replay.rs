@@ -0,0 +1,72 @@
+use crate::Emu;
+use alloc::vec::Vec;
+
+/// InputEvent is a single `keypress`, stamped with the number of ticks run before it.
+///
+/// Ticks that wait, on WAIT KEY or a DRAW waiting for the vertical blank, count too, so a
+/// key pressed and released during a wait keeps two different stamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputEvent {
+    pub tick: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// InputLog is every key press and release of a run, in the order they happened.
+///
+/// Replaying it with `apply_input_log` on an emulator started from the same ROM and the
+/// same seed (see `Emu::with_seed`) reproduces the run exactly, which makes it a small
+/// attachment for bug reports. With the `serde` feature enabled it can be serialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputLog {
+    pub events: Vec<InputEvent>,
+}
+
+impl Emu {
+    /// Starts recording every `keypress` into a new `InputLog`, dropping any log in progress.
+    ///
+    /// `reset` clears the events recorded so far, as the run starts over.
+    pub fn start_input_log(&mut self)
+    {
+        self.input_log = Some(InputLog::default());
+    }
+
+    /// Stops recording and returns the log, or `None` if nothing was being recorded.
+    pub fn take_input_log(&mut self) -> Option<InputLog>
+    {
+        self.input_log.take()
+    }
+
+    /// Replays `log`: every event is applied right before the tick following its stamp.
+    ///
+    /// Events stamped with a tick that has already passed are applied on the next `tick`.
+    /// Calling `keypress` during a replay still works and mixes with the replayed input.
+    pub fn apply_input_log(&mut self, log: &InputLog)
+    {
+        self.replay = log.events.iter().copied().collect();
+    }
+
+    // add a key change to the log being recorded
+    pub(crate) fn record_input(&mut self, key: usize, pressed: bool)
+    {
+        let tick = self.ticks;
+        if let Some(log) = self.input_log.as_mut() {
+            log.events.push(InputEvent { tick, key: key as u8, pressed });
+        }
+    }
+
+    // apply the replayed events that are due before the next tick
+    pub(crate) fn apply_replay(&mut self)
+    {
+        while let Some(event) = self.replay.front() {
+            if event.tick > self.ticks {
+                break;
+            }
+
+            self.keys[event.key as usize & 0x0F] = event.pressed;
+            self.replay.pop_front();
+        }
+    }
+}
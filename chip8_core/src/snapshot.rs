@@ -0,0 +1,216 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Chip8Error, Emu, AUDIO_BUFFER_SIZE, LARGE_RAM_SIZE, NUM_FLAGS, NUM_KEYS, NUM_PLANES, NUM_REGS,
+    MAX_STACK_SIZE, RAM_SIZE, SCREEN_SIZE, SCREEN_WORDS,
+};
+
+// Every snapshot starts with the magic bytes followed by the format version
+const MAGIC: &[u8; 4] = b"CH8S";
+const VERSION: u8 = 4;
+
+// Marks "no key held" for the WAIT KEY state
+const NO_KEY: u8 = 0xFF;
+
+/// Reader walks through a snapshot, failing with `BadSnapshot` when it runs out of bytes.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Chip8Error> {
+        if self.data.len() < len {
+            return Err(Chip8Error::BadSnapshot);
+        }
+
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, Chip8Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Chip8Error> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, Chip8Error> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn bool(&mut self) -> Result<bool, Chip8Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Chip8Error::BadSnapshot),
+        }
+    }
+}
+
+impl Emu {
+    /// Serializes all CPU state into a versioned binary snapshot.
+    ///
+    /// The snapshot starts with the magic bytes `CH8S` and a version byte, followed by
+    /// the registers, stack, timers, keys, halt state, counters, display and RAM. The stack is saved
+    /// with its size, which `load_state` restores too. The random number generator isn't part of the snapshot.
+    pub fn save_state(&self) -> Vec<u8>
+    {
+        let mut data = Vec::with_capacity(MAGIC.len() + self.memory_size() + SCREEN_SIZE / 4 + 128);
+
+        data.extend_from_slice(MAGIC);
+        data.push(VERSION);
+
+        data.extend_from_slice(&self.pc.to_le_bytes());
+        data.extend_from_slice(&self.i_reg.to_le_bytes());
+        data.extend_from_slice(&self.sp.to_le_bytes());
+        data.push(self.dt);
+        data.push(self.st);
+        data.extend_from_slice(&self.v_reg);
+        // The stack size can be changed, so it comes first
+        data.extend_from_slice(&(self.stack_size as u16).to_le_bytes());
+        for &addr in &self.stack[..self.stack_size] {
+            data.extend_from_slice(&addr.to_le_bytes());
+        }
+        data.extend(self.keys.iter().map(|&pressed| pressed as u8));
+        data.push(self.held_key.map_or(NO_KEY, |key| key as u8));
+        data.push(self.halted as u8);
+
+        data.push(self.hires as u8);
+        data.push(self.plane_mask);
+        data.push(self.large_memory as u8);
+        data.extend_from_slice(&self.flags);
+        data.extend_from_slice(&self.audio_buffer);
+        data.push(self.audio_pitch);
+        data.extend_from_slice(&self.cycles.to_le_bytes());
+        data.extend_from_slice(&self.frames.to_le_bytes());
+
+        // Pack every plane 8 pixels per byte, the most significant bit is the leftmost pixel
+        for plane in &self.screen {
+            for word in plane {
+                data.extend_from_slice(&word.to_be_bytes());
+            }
+        }
+
+        // Only the addressable memory is stored, which keeps 4KB snapshots small
+        data.extend_from_slice(&self.ram[..self.memory_size()]);
+
+        data
+    }
+
+    /// Restores CPU state from a snapshot created by `save_state`.
+    ///
+    /// The snapshot is fully validated before anything is applied, so on error the
+    /// emulator is left untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::BadSnapshot` if the magic bytes or version don't match,
+    /// or if the buffer is truncated or otherwise malformed.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), Chip8Error>
+    {
+        let mut reader = Reader { data };
+
+        if reader.bytes(MAGIC.len())? != MAGIC || reader.u8()? != VERSION {
+            return Err(Chip8Error::BadSnapshot);
+        }
+
+        let pc = reader.u16()?;
+        let i_reg = reader.u16()?;
+        let sp = reader.u16()?;
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+
+        let mut v_reg = [0; NUM_REGS];
+        v_reg.copy_from_slice(reader.bytes(NUM_REGS)?);
+
+        let stack_size = reader.u16()? as usize;
+        if stack_size == 0 || stack_size > MAX_STACK_SIZE {
+            return Err(Chip8Error::BadSnapshot);
+        }
+        let mut stack = [0; MAX_STACK_SIZE];
+        for addr in stack[..stack_size].iter_mut() {
+            *addr = reader.u16()?;
+        }
+
+        let mut keys = [false; NUM_KEYS];
+        for pressed in keys.iter_mut() {
+            *pressed = reader.bool()?;
+        }
+
+        let held_key = match reader.u8()? {
+            NO_KEY => None,
+            key if (key as usize) < NUM_KEYS => Some(key as usize),
+            _ => return Err(Chip8Error::BadSnapshot),
+        };
+        let halted = reader.bool()?;
+
+        let hires = reader.bool()?;
+        let plane_mask = reader.u8()?;
+        let large_memory = reader.bool()?;
+
+        let mut flags = [0; NUM_FLAGS];
+        flags.copy_from_slice(reader.bytes(NUM_FLAGS)?);
+
+        let mut audio_buffer = [0; AUDIO_BUFFER_SIZE];
+        audio_buffer.copy_from_slice(reader.bytes(AUDIO_BUFFER_SIZE)?);
+        let audio_pitch = reader.u8()?;
+        let cycles = reader.u64()?;
+        let frames = reader.u64()?;
+
+        let mut screen = [[0; SCREEN_WORDS]; NUM_PLANES];
+        for plane in screen.iter_mut() {
+            let packed = reader.bytes(SCREEN_SIZE / 8)?;
+            for (word, bytes) in plane.iter_mut().zip(packed.chunks(8)) {
+                let mut be_bytes = [0; 8];
+                be_bytes.copy_from_slice(bytes);
+                *word = u64::from_be_bytes(be_bytes);
+            }
+        }
+
+        let memory_size = if large_memory { LARGE_RAM_SIZE } else { RAM_SIZE };
+        let ram = reader.bytes(memory_size)?;
+
+        // Reject trailing bytes and impossible stack pointers
+        if !reader.data.is_empty() || sp as usize > stack_size || plane_mask > 0b11 {
+            return Err(Chip8Error::BadSnapshot);
+        }
+
+        // Everything is valid, so apply it
+        self.pc = pc;
+        self.i_reg = i_reg;
+        self.sp = sp;
+        self.dt = dt;
+        self.update_sound_timer(st);
+        self.v_reg = v_reg;
+        self.stack = stack;
+        self.stack_size = stack_size;
+        self.keys = keys;
+        self.held_key = held_key;
+        // PC is still on the WAIT KEY, which waits again on the next tick
+        self.waiting_for_key = None;
+        self.waiting_for_vblank = false;
+        self.halted = halted;
+        self.hires = hires;
+        self.plane_mask = plane_mask;
+        self.large_memory = large_memory;
+        self.flags = flags;
+        self.audio_buffer = audio_buffer;
+        self.audio_pitch = audio_pitch;
+        self.cycles = cycles;
+        self.frames = frames;
+        self.screen = screen;
+        self.screen_dirty = true;
+        self.intensity = [0; SCREEN_SIZE];
+        self.sync_visible_screen();
+        self.ram[..memory_size].copy_from_slice(ram);
+        self.ram[memory_size..].fill(0);
+
+        Ok(())
+    }
+}
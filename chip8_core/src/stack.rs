@@ -0,0 +1,85 @@
+use crate::{Chip8Error, Emu, MAX_STACK_SIZE};
+
+/// StackOverflowPolicy selects what a CALL does when the stack is already full.
+///
+/// Interpreters disagree on this, and a ROM that recurses too deeply by accident may only
+/// run on some of them. Like the quirks, the policy is a machine setting, so it is kept
+/// across `reset`.
+///
+/// - `Error`: the CALL fails with `Chip8Error::StackOverflow`. This is the default.
+/// - `Ignore`: the CALL is skipped, execution continues with the next instruction.
+/// - `Wrap`: the stack is a ring, the CALL overwrites the oldest return address. A RET on
+///   an empty stack wraps around the other way instead of underflowing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StackOverflowPolicy {
+    #[default]
+    Error,
+    Ignore,
+    Wrap,
+}
+
+impl Emu {
+    /// Sets how many return addresses the stack holds, 16 by default.
+    ///
+    /// Growing the stack keeps the addresses on it, shrinking it drops the newest ones.
+    /// The size is clamped to 1 through `MAX_STACK_SIZE`. This is a machine setting, so it is kept across `reset`.
+    pub fn set_stack_size(&mut self, size: usize) {
+        let size = size.clamp(1, MAX_STACK_SIZE);
+        self.stack[size..].fill(0);
+        self.stack_size = size;
+        self.sp = self.sp.min(size as u16);
+    }
+
+    // return how many return addresses the stack holds
+    pub fn stack_size(&self) -> usize {
+        self.stack_size
+    }
+
+    /// Selects what a CALL does when the stack is full, see `StackOverflowPolicy`.
+    ///
+    /// This is a machine setting, so it is kept across `reset`.
+    pub fn set_stack_overflow_policy(&mut self, policy: StackOverflowPolicy) {
+        self.stack_overflow_policy = policy;
+    }
+
+    // return what a CALL does when the stack is full
+    pub fn stack_overflow_policy(&self) -> StackOverflowPolicy {
+        self.stack_overflow_policy
+    }
+
+    // returns whether `val` was pushed, `Ignore` drops it when the stack is full
+    pub(crate) fn push(&mut self, val: u16) -> Result<bool, Chip8Error>
+    {
+        // Too many nested CALLs, there is no room left for the return address
+        if self.sp as usize >= self.stack_size {
+            match self.stack_overflow_policy {
+                StackOverflowPolicy::Error => return Err(Chip8Error::StackOverflow),
+                StackOverflowPolicy::Ignore => return Ok(false),
+                StackOverflowPolicy::Wrap => self.sp = 0,
+            }
+        }
+
+        self.stack[self.sp as usize] = val;
+
+        self.sp += 1;
+
+        Ok(true)
+    }
+
+    pub(crate) fn pop(&mut self) -> Result<u16, Chip8Error>
+    {
+        // A RET without a matching CALL
+        if self.sp == 0 {
+            if self.stack_overflow_policy != StackOverflowPolicy::Wrap {
+                return Err(Chip8Error::StackUnderflow);
+            }
+
+            self.sp = self.stack_size as u16;
+        }
+
+        self.sp -= 1;
+
+        Ok(self.stack[self.sp as usize])
+    }
+}
@@ -0,0 +1,45 @@
+use core::fmt;
+
+use crate::Emu;
+
+/// A one line summary of the CPU, e.g.
+/// `Emu { PC=0x200 I=0x000 SP=0 DT=0 ST=0 V=[00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00] }`.
+///
+/// Memory and the display are left out, they would drown the registers.
+impl fmt::Debug for Emu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Emu {{ PC=0x{:03X} I=0x{:03X} SP={} DT={} ST={} V=[",
+            self.pc, self.i_reg, self.sp, self.dt, self.st
+        )?;
+        for (idx, v) in self.v_reg.iter().enumerate() {
+            let sep = if idx == 0 { "" } else { " " };
+            write!(f, "{}{:02X}", sep, v)?;
+        }
+        write!(f, "] }}")
+    }
+}
+
+/// A register table, four V registers per line:
+///
+/// ```text
+/// PC 0x200  I 0x000  SP 0  DT 0  ST 0
+/// V0 00  V1 00  V2 00  V3 00
+/// ...
+/// VC 00  VD 00  VE 00  VF 00
+/// ```
+impl fmt::Display for Emu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PC 0x{:03X}  I 0x{:03X}  SP {}  DT {}  ST {}",
+            self.pc, self.i_reg, self.sp, self.dt, self.st
+        )?;
+        for (idx, v) in self.v_reg.iter().enumerate() {
+            let sep = if idx % 4 == 0 { "\n" } else { "  " };
+            write!(f, "{}V{:X} {:02X}", sep, idx, v)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1850 @@
+use crate::*;
+
+#[test]
+fn drawing_on_plane_2_leaves_plane_1_off() {
+    // PLANE 2; LD I, 0x206; DRW V0, V0, 1; the sprite row 0xF0
+    let mut emu = Emu::new();
+    emu.load(&[0xF2, 0x01, 0xA2, 0x06, 0xD0, 0x01, 0xF0]);
+    for _ in 0..3 {
+        emu.tick();
+    }
+    let planes = emu.get_display_planes();
+    assert!(planes[0].iter().all(|&lit| !lit));
+    let lit: Vec<usize> = planes[1].iter().enumerate().filter(|(_, &lit)| lit).map(|(idx, _)| idx).collect();
+    assert_eq!(lit, [0, 1, 2, 3]);
+}
+
+#[test]
+fn same_seed_gives_the_same_random_numbers() {
+    // RND V0..V3, then draw with them and loop
+    let program = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0x3F, 0xC3, 0x1F, 0xD2, 0x35, 0x12, 0x00];
+    let run_seeded = |seed| {
+        let mut emu = Emu::with_seed(seed);
+        emu.load(&program);
+        for _ in 0..600 {
+            emu.tick();
+        }
+        (emu.v_reg, emu.screen)
+    };
+
+    assert_eq!(run_seeded(42), run_seeded(42));
+    assert_ne!(run_seeded(42), run_seeded(43));
+}
+
+#[test]
+fn ret_past_the_matching_calls_underflows() {
+    // CALL 0x204; RET; RET
+    let mut emu = Emu::new();
+    emu.load(&[0x22, 0x04, 0x00, 0xEE, 0x00, 0xEE]);
+    emu.try_tick().unwrap();
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc, 0x202);
+    assert_eq!(emu.try_tick(), Err(Chip8Error::StackUnderflow));
+    assert_eq!(emu.sp, 0);
+}
+
+#[test]
+fn seventeen_nested_calls_overflow() {
+    // 17 CALLs in a row, each to the next one
+    let program: Vec<u8> = (1..=17u16).flat_map(|n| (0x2000 | (0x200 + 2 * n)).to_be_bytes()).collect();
+    let mut emu = Emu::new();
+    emu.load(&program);
+    for _ in 0..16 {
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(emu.try_tick(), Err(Chip8Error::StackOverflow));
+    assert_eq!(emu.sp, 16);
+    assert_eq!(emu.stack(), (1..=16).map(|n| 0x200 + 2 * n).collect::<Vec<u16>>());
+}
+
+#[test]
+fn disassemble_formats_each_mnemonic() {
+    let cases = [
+        (0x632A, "LD V3, 0x2A"),
+        (0xD015, "DRW V0, V1, 5"),
+        (0x00EE, "RET"),
+        (0xE000, "DW 0xE000"),
+    ];
+    for (op, text) in cases {
+        assert_eq!(disasm::disassemble(op), text, "opcode 0x{:04X}", op);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip_keeps_the_state() {
+    // Unoptimized, deserializing the RAM and screen arrays takes more than the 2MB of a test thread
+    let round_trip = std::thread::Builder::new().stack_size(32 << 20).spawn(|| {
+        // LD V0, 0x2A; LD F, V0; DRW V0, V0, 5; LD DT, V0; LD ST, V0; CALL 0x20E; then loop
+        let mut emu = Emu::with_seed(3);
+        emu.load(&[0x60, 0x2A, 0xF0, 0x29, 0xD0, 0x05, 0xF0, 0x15, 0xF0, 0x18, 0x22, 0x0E, 0x00, 0x00, 0x12, 0x0E]);
+        for _ in 0..10 {
+            emu.tick();
+        }
+        emu.tick_timers();
+
+        let json = serde_json::to_string(&emu).unwrap();
+        let restored: Emu = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.pc(), emu.pc());
+        assert_eq!(restored.registers(), emu.registers());
+        assert_eq!(restored.i_reg(), emu.i_reg());
+        assert_eq!(restored.stack(), emu.stack());
+        assert_eq!((restored.delay_timer(), restored.sound_timer()), (0x29, 0x29));
+        assert_eq!(restored.get_display(), emu.get_display());
+        assert_eq!(restored.ram, emu.ram);
+    });
+
+    round_trip.unwrap().join().unwrap();
+}
+
+#[test]
+fn bad_snapshots_leave_the_emulator_untouched() {
+    let mut saved = Emu::with_seed(0);
+    saved.load(&[0x60, 0x2A, 0x12, 0x02]);
+    let snapshot = saved.save_state();
+
+    // LD V1, 0x23; JP 0x200
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x61, 0x23, 0x12, 0x00]);
+    for _ in 0..3 {
+        emu.tick();
+    }
+    let before = emu.save_state();
+
+    let mut bad_magic = snapshot.clone();
+    bad_magic[0] = b'X';
+    let mut wrong_version = snapshot.clone();
+    wrong_version[4] += 1;
+    let truncated = &snapshot[..snapshot.len() - 1];
+
+    for data in [&bad_magic[..], &wrong_version[..], truncated, &[]] {
+        assert_eq!(emu.load_state(data), Err(Chip8Error::BadSnapshot));
+        assert_eq!(emu.save_state(), before);
+    }
+}
+
+#[test]
+fn stepping_back_restores_the_state_before_the_steps() {
+    let mut emu = Emu::with_seed(0);
+    emu.enable_history(16);
+    assert!(!emu.step_back());
+
+    // LD V0, 5; LD F, V0; DRW V0, V0, 5; ADD V0, 1; CALL 0x200
+    emu.load(&[0x60, 0x05, 0xF0, 0x29, 0xD0, 0x05, 0x70, 0x01, 0x22, 0x00]);
+    let start = emu.save_state();
+
+    for _ in 0..10 {
+        emu.try_tick().unwrap();
+    }
+    assert_ne!(emu.save_state(), start);
+
+    for _ in 0..10 {
+        assert!(emu.step_back());
+    }
+    assert_eq!(emu.save_state(), start);
+    assert!(!emu.step_back());
+    assert_eq!(emu.save_state(), start);
+}
+
+#[test]
+fn tick_until_breakpoint_stops_at_the_breakpoint() {
+    // LD V0, 1; ADD V0, 1; ADD V0, 1; JP 0x202
+    let mut emu = Emu::new();
+    emu.load(&[0x60, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x02]);
+    emu.add_breakpoint(0x204);
+
+    assert_eq!(emu.tick_until_breakpoint(100), Ok(StopReason::Breakpoint(0x204)));
+    assert_eq!(emu.pc(), 0x204);
+    assert_eq!(emu.registers()[0], 2);
+
+    // Resuming runs the instruction at the breakpoint, then stops on it again a loop later
+    assert_eq!(emu.tick_until_breakpoint(100), Ok(StopReason::Breakpoint(0x204)));
+    assert_eq!(emu.registers()[0], 4);
+
+    assert!(emu.remove_breakpoint(0x204));
+    assert_eq!(emu.tick_until_breakpoint(10), Ok(StopReason::Exhausted));
+}
+
+#[test]
+fn trace_hook_sees_every_instruction_in_order() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&seen);
+
+    // LD V0, 1; CALL 0x206; JP 0x200; RET
+    let mut emu = Emu::new();
+    emu.load(&[0x60, 0x01, 0x22, 0x06, 0x12, 0x00, 0x00, 0xEE]);
+    emu.set_trace_hook(Box::new(move |addr, op| log.borrow_mut().push((addr, op))));
+    for _ in 0..5 {
+        emu.try_tick().unwrap();
+    }
+
+    assert_eq!(*seen.borrow(), vec![
+        (0x200, 0x6001),
+        (0x202, 0x2206),
+        (0x206, 0x00EE),
+        (0x204, 0x1200),
+        (0x200, 0x6001),
+    ]);
+
+    emu.clear_trace_hook();
+    emu.try_tick().unwrap();
+    assert_eq!(seen.borrow().len(), 5);
+}
+
+#[test]
+fn hundred_ticks_count_a_hundred_cycles() {
+    let mut emu = Emu::new();
+    emu.load(&[0x70, 0x01, 0x12, 0x00]);
+    for _ in 0..100 {
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(emu.cycle_count(), 100);
+
+    emu.reset();
+    assert_eq!(emu.cycle_count(), 0);
+}
+
+#[test]
+fn beep_stops_when_the_sound_timer_runs_out() {
+    // LD V0, 2; LD ST, V0
+    let mut emu = Emu::new();
+    emu.load(&[0x60, 0x02, 0xF0, 0x18]);
+    emu.try_tick().unwrap();
+    assert!(!emu.is_beeping());
+
+    emu.try_tick().unwrap();
+    assert!(emu.is_beeping());
+    emu.tick_timers();
+    assert!(emu.is_beeping());
+    emu.tick_timers();
+    assert!(!emu.is_beeping());
+    assert_eq!(emu.sound_timer(), 0);
+}
+
+// draw the first `rows` rows of the "0" at (x, y) with the given sprite quirks, then return the first plane
+fn draw_zero(x: u8, y: u8, rows: u8, wrap_sprite_origin: bool, clip_sprite_body: bool) -> Vec<bool> {
+    // LD V0, x; LD V1, y; LD V2, 0; LD F, V2; DRW V0, V1, rows
+    let mut emu = Emu::with_seed(0);
+    emu.set_quirks(Quirks { wrap_sprite_origin, clip_sprite_body, ..Quirks::DEFAULT });
+    emu.load(&[0x60, x, 0x61, y, 0x62, 0x00, 0xF2, 0x29, 0xD0, 0x10 | rows]);
+    for _ in 0..5 {
+        emu.try_tick().unwrap();
+    }
+    emu.get_display().to_vec()
+}
+
+// the lit columns of the top row after drawing the top row of the "0" at (x, 0)
+fn draw_at_right_edge(x: u8, wrap_sprite_origin: bool, clip_sprite_body: bool) -> Vec<usize> {
+    let display = draw_zero(x, 0, 1, wrap_sprite_origin, clip_sprite_body);
+    (0..SCREEN_WIDTH).filter(|&col| display[col]).collect()
+}
+
+// the lit rows of the first column after drawing the "0" at (0, y)
+fn draw_at_bottom_edge(y: u8, wrap_sprite_origin: bool, clip_sprite_body: bool) -> Vec<usize> {
+    let display = draw_zero(0, y, 5, wrap_sprite_origin, clip_sprite_body);
+    (0..SCREEN_HEIGHT).filter(|&row| display[row * SCREEN_WIDTH]).collect()
+}
+
+#[test]
+fn sprite_wraps_at_the_right_edge_without_clipping() {
+    assert_eq!(draw_at_right_edge(62, true, false), vec![0, 1, 62, 63]);
+    assert_eq!(draw_at_right_edge(66, true, false), vec![2, 3, 4, 5]);
+}
+
+#[test]
+fn sprite_clips_at_the_right_edge_with_clipping() {
+    assert_eq!(draw_at_right_edge(60, true, true), vec![60, 61, 62, 63]);
+    assert_eq!(draw_at_right_edge(62, true, true), vec![62, 63]);
+    // The starting coordinate still wraps
+    assert_eq!(draw_at_right_edge(66, true, true), vec![2, 3, 4, 5]);
+}
+
+#[test]
+fn sprite_wraps_at_the_bottom_edge_without_clipping() {
+    assert_eq!(draw_at_bottom_edge(29, true, false), vec![0, 1, 29, 30, 31]);
+}
+
+#[test]
+fn sprite_clips_at_the_bottom_edge_with_clipping() {
+    assert_eq!(draw_at_bottom_edge(29, true, true), vec![29, 30, 31]);
+}
+
+#[test]
+fn sprite_origin_below_the_screen_follows_both_quirks() {
+    assert_eq!(draw_at_bottom_edge(34, true, false), vec![2, 3, 4, 5, 6]);
+    assert_eq!(draw_at_bottom_edge(34, true, true), vec![2, 3, 4, 5, 6]);
+    assert_eq!(draw_at_bottom_edge(30, false, true), vec![30, 31]);
+    assert_eq!(draw_at_bottom_edge(34, false, true), Vec::<usize>::new());
+}
+
+#[test]
+fn display_wait_holds_the_cpu_until_tick_timers() {
+    // DRW V0, V0, 1; ADD V1, 1
+    let mut emu = Emu::with_seed(0);
+    emu.set_quirks(Quirks { display_wait: true, ..Quirks::DEFAULT });
+    emu.load(&[0xD0, 0x01, 0x71, 0x01]);
+    emu.try_tick().unwrap();
+    assert!(emu.waiting_for_vblank());
+
+    for _ in 0..5 {
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.registers()[1], 0);
+
+    emu.tick_timers();
+    assert!(!emu.waiting_for_vblank());
+    emu.try_tick().unwrap();
+    assert_eq!(emu.registers()[1], 1);
+}
+
+#[test]
+fn builder_applies_every_setting() {
+    let quirks = Quirks { clip_sprite_body: true, display_wait: true, ..Quirks::DEFAULT };
+    let emu = EmuBuilder::default()
+        .quirks(quirks)
+        .hires(true)
+        .large_memory(true)
+        .target_ips(900)
+        .start_address(0x600)
+        .build()
+        .unwrap();
+
+    assert_eq!(emu.quirks(), quirks);
+    assert!(emu.hires);
+    assert_eq!(emu.memory_size(), LARGE_RAM_SIZE);
+    assert_eq!(emu.target_ips(), 900);
+    assert_eq!(emu.start_address(), 0x600);
+    assert_eq!(emu.pc(), 0x600);
+
+    assert_eq!(EmuBuilder::default().build().unwrap().save_state(), Emu::new().save_state());
+}
+
+#[test]
+fn builder_rejects_a_start_address_out_of_memory() {
+    let built = EmuBuilder::default().start_address(0x1000).build();
+    assert_eq!(built.err(), Some(Chip8Error::AddressOutOfRange(0x1000)));
+}
+
+#[test]
+fn builder_seed_matches_with_seed() {
+    // RND V0, 0xFF
+    let mut built = EmuBuilder::default().seed(7).build().unwrap();
+    let mut seeded = Emu::with_seed(7);
+    for emu in [&mut built, &mut seeded] {
+        emu.load(&[0xC0, 0xFF]);
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(built.registers(), seeded.registers());
+}
+
+#[test]
+fn clone_doesnt_follow_the_original() {
+    // LD V0, 0; LD F, V0; DRW V0, V0, 5; ADD V1, 1; JP 0x206
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x71, 0x01, 0x12, 0x06]);
+    emu.try_tick().unwrap();
+    let copy = emu.clone();
+
+    for _ in 0..6 {
+        emu.try_tick().unwrap();
+    }
+    assert!(emu.get_display()[0]);
+    assert_eq!(emu.registers()[1], 2);
+
+    assert_eq!(copy.pc(), 0x202);
+    assert_eq!(copy.registers(), &[0; NUM_REGS]);
+    assert!(copy.get_display().iter().all(|&lit| !lit));
+}
+
+#[test]
+fn run_cycles_runs_a_counting_loop() {
+    // LD V0, 0; ADD V0, 1; ADD V1, 2; JP 0x202
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x60, 0x00, 0x70, 0x01, 0x71, 0x02, 0x12, 0x02]);
+
+    // 1 + 3 loops of 3 instructions + 2 more
+    assert_eq!(emu.run_cycles(12), 12);
+    assert_eq!(emu.registers()[0], 4);
+    assert_eq!(emu.registers()[1], 8);
+    assert_eq!(emu.pc(), 0x206);
+}
+
+#[test]
+fn run_cycles_stops_at_an_error() {
+    // LD V0, 1; then an unknown opcode
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x60, 0x01, 0xFF, 0xFF]);
+    assert_eq!(emu.run_cycles(10), 1);
+    assert_eq!(emu.registers()[0], 1);
+}
+
+// get_display unpacks get_display_bits, the most significant bit of each word first
+fn assert_display_matches_bits(emu: &Emu) {
+    let bits = emu.get_display_bits();
+    let unpacked: Vec<bool> = (0..bits.len() * 64).map(|idx| bits[idx / 64] >> (63 - idx % 64) & 1 == 1).collect();
+    assert_eq!(emu.get_display(), unpacked);
+}
+
+#[test]
+fn display_and_display_bits_agree() {
+    // LD V0, 61; LD V1, 3; LD V2, 0; LD F, V2; DRW V0, V1, 5; SCR; SCD 2; HIGH; DRW V0, V1, 5; SCL
+    let mut emu = Emu::new();
+    emu.load(&[0x60, 61, 0x61, 3, 0x62, 0x00, 0xF2, 0x29, 0xD0, 0x15, 0x00, 0xFB, 0x00, 0xC2, 0x00, 0xFF, 0xD0, 0x15, 0x00, 0xFC]);
+
+    for _ in 0..10 {
+        emu.try_tick().unwrap();
+        assert_display_matches_bits(&emu);
+    }
+    assert!(emu.get_display().iter().any(|&lit| lit));
+}
+
+#[test]
+fn decode_sorts_every_opcode_into_its_instruction() {
+    use Instruction::*;
+
+    let table = [
+        (0x0000, Nop), (0x00E0, ClearScreen), (0x00EE, Return), (0x00C3, ScrollDown(3)),
+        (0x00FB, ScrollRight), (0x00FC, ScrollLeft), (0x00FD, Exit), (0x00FE, LowRes),
+        (0x00FF, HighRes), (0x1ABC, Jump(0xABC)), (0x2ABC, Call(0xABC)),
+        (0x3A12, SkipEqImm { x: 0xA, nn: 0x12 }), (0x4A12, SkipNeImm { x: 0xA, nn: 0x12 }),
+        (0x5AB0, SkipEqReg { x: 0xA, y: 0xB }), (0x6A12, SetReg { x: 0xA, nn: 0x12 }),
+        (0x7A12, AddImm { x: 0xA, nn: 0x12 }), (0x8AB0, Move { x: 0xA, y: 0xB }),
+        (0x8AB1, Or { x: 0xA, y: 0xB }), (0x8AB2, And { x: 0xA, y: 0xB }),
+        (0x8AB3, Xor { x: 0xA, y: 0xB }), (0x8AB4, AddReg { x: 0xA, y: 0xB }),
+        (0x8AB5, SubReg { x: 0xA, y: 0xB }), (0x8AB6, ShiftRight { x: 0xA, y: 0xB }),
+        (0x8AB7, SubN { x: 0xA, y: 0xB }), (0x8ABE, ShiftLeft { x: 0xA, y: 0xB }),
+        (0x9AB0, SkipNeReg { x: 0xA, y: 0xB }), (0xAABC, SetIndex(0xABC)),
+        (0xBABC, JumpOffset(0xABC)), (0xCA12, Random { x: 0xA, nn: 0x12 }),
+        (0xDAB5, Draw { x: 0xA, y: 0xB, n: 5 }), (0xEA9E, SkipKeyPressed { x: 0xA }),
+        (0xEAA1, SkipKeyReleased { x: 0xA }), (0xF000, LoadLongIndex), (0xF201, SelectPlanes(2)),
+        (0xF002, LoadAudio), (0xFA07, GetDelay { x: 0xA }), (0xFA0A, WaitKey { x: 0xA }),
+        (0xFA15, SetDelay { x: 0xA }), (0xFA18, SetSound { x: 0xA }), (0xFA1E, AddIndex { x: 0xA }),
+        (0xFA29, FontChar { x: 0xA }), (0xFA30, BigFontChar { x: 0xA }), (0xFA33, Bcd { x: 0xA }),
+        (0xFA3A, SetPitch { x: 0xA }), (0xFA55, StoreRegs { x: 0xA }), (0xFA65, LoadRegs { x: 0xA }),
+        (0xFA75, StoreFlags { x: 0xA }), (0xFA85, LoadFlags { x: 0xA }),
+        (0x5AB1, Unknown(0x5AB1)), (0x8AB8, Unknown(0x8AB8)), (0xEA00, Unknown(0xEA00)), (0xFAFF, Unknown(0xFAFF)),
+    ];
+
+    for (op, instruction) in table {
+        assert_eq!(decode(op), instruction, "0x{:04X}", op);
+    }
+}
+
+#[test]
+fn bcd_of_every_byte() {
+    for value in 0..=255u8 {
+        // LD B, V1
+        let mut emu = Emu::with_seed(0);
+        emu.load(&[0xF1, 0x33]);
+        emu.v_reg[1] = value;
+        emu.i_reg = 0x300;
+        emu.try_tick().unwrap();
+
+        let digits = &emu.ram[0x300..0x303];
+        assert_eq!(digits, &[value / 100, value / 10 % 10, value % 10], "{}", value);
+        assert_eq!(digits[0] as u32 * 100 + digits[1] as u32 * 10 + digits[2] as u32, value as u32);
+    }
+}
+
+#[test]
+fn store_regs_wraps_around_the_end_of_memory() {
+    // LD [I], V3
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0xF3, 0x55]);
+    emu.v_reg[..4].copy_from_slice(&[1, 2, 3, 4]);
+    emu.i_reg = 0xFFE;
+    emu.try_tick().unwrap();
+
+    assert_eq!(&emu.ram[0xFFE..0x1000], &[1, 2]);
+    assert_eq!(&emu.ram[0x000..0x002], &[3, 4]);
+    assert_eq!(emu.i_reg, 0xFFE);
+}
+
+#[test]
+fn load_regs_wraps_around_the_end_of_memory() {
+    // LD V3, [I]
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0xF3, 0x65]);
+    emu.ram[0xFFE..0x1000].copy_from_slice(&[1, 2]);
+    emu.ram[0x000..0x002].copy_from_slice(&[3, 4]);
+    emu.i_reg = 0xFFE;
+    emu.try_tick().unwrap();
+
+    assert_eq!(&emu.v_reg[..4], &[1, 2, 3, 4]);
+    assert_eq!(emu.i_reg, 0xFFE);
+}
+
+#[test]
+fn out_of_range_keys_are_ignored() {
+    let mut emu = Emu::with_seed(0);
+    emu.keypress(20, true);
+    emu.keypress(usize::MAX, true);
+    assert_eq!(emu.keys, [false; NUM_KEYS]);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn load_path_reads_the_rom_and_rejects_large_ones() {
+    let dir = std::env::temp_dir();
+    let rom_path = dir.join(format!("chip8_core_rom_{}.ch8", std::process::id()));
+    let large_path = dir.join(format!("chip8_core_large_{}.ch8", std::process::id()));
+    std::fs::write(&rom_path, [0x60, 0x2A]).unwrap();
+    std::fs::write(&large_path, vec![0; 0x1000]).unwrap();
+
+    let mut emu = Emu::with_seed(0);
+    let loaded = emu.load_path(&rom_path);
+    let too_large = Emu::with_seed(0).load_path(&large_path);
+    let missing = Emu::with_seed(0).load_path(dir.join("chip8_core_no_such_rom.ch8"));
+    std::fs::remove_file(&rom_path).unwrap();
+    std::fs::remove_file(&large_path).unwrap();
+
+    loaded.unwrap();
+    emu.try_tick().unwrap();
+    assert_eq!(emu.registers()[0], 0x2A);
+
+    let err = too_large.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(err.get_ref().and_then(|err| err.downcast_ref::<Chip8Error>()), Some(&Chip8Error::RomTooLarge(0x1000)));
+
+    assert_eq!(missing.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn opcode_coverage_counts_each_mnemonic() {
+    // LD V0, 0x01; LD V1, 0x02; ADD V0, V1; LD I, long 0x0300; CLS; JP 0x200
+    let emu = Emu::from_rom(&[0x60, 0x01, 0x61, 0x02, 0x80, 0x14, 0xF0, 0x00, 0x03, 0x00, 0x00, 0xE0, 0x12, 0x00]).unwrap();
+
+    let counts: Vec<_> = emu.opcode_coverage().into_iter().collect();
+    assert_eq!(counts, [("ADD Vx, Vy", 1), ("CLS", 1), ("JP addr", 1), ("LD I, long", 1), ("LD Vx, byte", 2)]);
+}
+
+#[test]
+fn lit_pixels_lists_the_drawn_pixels_row_by_row() {
+    // LD V0, 5; LD V1, 3; LD I, 0x300; DRW V0, V1, 2
+    let mut emu = Emu::from_rom(&[0x60, 0x05, 0x61, 0x03, 0xA3, 0x00, 0xD0, 0x12]).unwrap();
+    emu.ram[0x300..0x302].copy_from_slice(&[0b1010_0000, 0b0100_0000]);
+    emu.run_cycles(4);
+
+    assert_eq!(emu.lit_pixels().collect::<Vec<_>>(), [(5, 3), (7, 3), (6, 4)]);
+}
+
+#[test]
+fn render_rgba_colors_every_pixel() {
+    const FG: [u8; 4] = [0xFF, 0x80, 0x00, 0xFF];
+    const BG: [u8; 4] = [0x10, 0x20, 0x30, 0xFF];
+
+    // LD V0, 1; LD I, 0x300; DRW V0, V0, 1 lights (1, 1)
+    let mut emu = Emu::from_rom(&[0x60, 0x01, 0xA3, 0x00, 0xD0, 0x01]).unwrap();
+    emu.ram[0x300] = 0b1000_0000;
+    emu.run_cycles(3);
+
+    let rgba = emu.render_rgba(FG, BG, 1);
+    assert_eq!(rgba.len(), SCREEN_WIDTH * SCREEN_HEIGHT * 4);
+    let pixel = |x: usize, y: usize| &rgba[(y * SCREEN_WIDTH + x) * 4..][..4];
+    assert_eq!(pixel(1, 1), FG);
+    assert_eq!(pixel(0, 1), BG);
+
+    emu.hires = true;
+    assert_eq!(emu.render_rgba(FG, BG, 1).len(), HIRES_SCREEN_WIDTH * HIRES_SCREEN_HEIGHT * 4);
+    assert_eq!(emu.render_rgba(FG, BG, 2).len(), HIRES_SCREEN_WIDTH * 2 * HIRES_SCREEN_HEIGHT * 2 * 4);
+}
+
+#[test]
+fn diff_names_the_changed_register() {
+    let emu = Emu::with_seed(0);
+    let mut other = emu.clone();
+    assert!(emu == other);
+    assert!(emu.diff(&other).is_empty());
+
+    other.v_reg[3] = 0x2A;
+    assert!(emu != other);
+    assert_eq!(emu.diff(&other), ["V3: 0x00 != 0x2A"]);
+}
+
+#[test]
+fn ticks_per_frame_rounds_the_target_speed() {
+    let mut emu = Emu::new();
+    assert_eq!(emu.ticks_per_frame(), 10);
+
+    emu.set_target_ips(700);
+    assert_eq!(emu.ticks_per_frame(), 12);
+    emu.set_target_ips(60);
+    assert_eq!(emu.ticks_per_frame(), 1);
+    emu.set_target_ips(0);
+    assert_eq!(emu.ticks_per_frame(), 1);
+
+    assert_eq!(EmuBuilder::default().target_ips(1200).build().unwrap().ticks_per_frame(), 20);
+}
+
+#[cfg(feature = "flate2")]
+fn gzip(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+#[cfg(feature = "flate2")]
+fn load_gz_loads_a_compressed_rom() {
+    let mut emu = Emu::with_seed(0);
+    emu.load_gz(&gzip(&[0x60, 0x2A])).unwrap();
+    emu.try_tick().unwrap();
+    assert_eq!(emu.registers()[0], 0x2A);
+
+    let mut emu = Emu::with_seed(0);
+    assert_eq!(emu.load_gz(&[0x60, 0x2A]), Err(Chip8Error::BadGzip));
+    assert_eq!(emu.load_gz(&gzip(&[0; 0x1000])), Err(Chip8Error::RomTooLarge(0xE01)));
+    assert!(emu.ram[0x200..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn detect_quirks_knows_the_bundled_roms() {
+    let blitz = include_bytes!("../../web/roms/BLITZ");
+    assert_eq!(detect_quirks(blitz), Some(Quirks { clip_sprite_body: true, ..Quirks::DEFAULT }));
+    assert_eq!(detect_quirks(include_bytes!("../../web/roms/PONG")), Some(Quirks::default()));
+
+    // A ROM that isn't in the table, even one byte off a known one, is left to the caller
+    assert_eq!(detect_quirks(&blitz[1..]), None);
+    assert_eq!(detect_quirks(include_bytes!("../../web/roms/MAZE")), None);
+    assert_eq!(detect_quirks(&[]), None);
+}
+
+#[test]
+fn assembling_the_disassembly_gives_back_the_opcode() {
+    for op in 0..=0xFFFFu16 {
+        // A lone F000 is the first half of the long load, see below
+        if op == 0xF000 {
+            continue;
+        }
+
+        let rom = asm::assemble(&disasm::disassemble(op)).unwrap();
+        let expected = match decode(op) {
+            // The disassembly leaves out VY, which only the shift quirk reads
+            Instruction::ShiftRight { .. } | Instruction::ShiftLeft { .. } => op & 0xFF0F,
+            _ => op,
+        };
+        assert_eq!(rom, expected.to_be_bytes(), "{:04X}", op);
+    }
+
+    let long_load = [0xF0, 0x00, 0x12, 0x34];
+    let listing = disasm::disassemble_range(&long_load, 0, 1);
+    assert_eq!(asm::assemble(&listing[0].1).unwrap(), long_load);
+}
+
+#[test]
+fn assemble_resolves_labels_and_directives() {
+    let src = "start: LD I, sprite ; point at the data\n  jp start\nsprite: DB 0x3C, 0b01000010\nDW 0x1234";
+    assert_eq!(asm::assemble(src).unwrap(), [0xA2, 0x04, 0x12, 0x00, 0x3C, 0x42, 0x12, 0x34]);
+}
+
+#[test]
+fn assemble_reports_the_line_of_each_error() {
+    use asm::AsmError;
+
+    let cases = [
+        ("CLS\nFOO V1", AsmError::UnknownMnemonic { line: 2, mnemonic: "FOO".into() }),
+        ("LD DT, DT", AsmError::BadOperands { line: 1 }),
+        ("LD V1, 0x100", AsmError::BadValue { line: 1, value: "256".into() }),
+        ("ADD VG, V1", AsmError::BadValue { line: 1, value: "VG".into() }),
+        ("\nJP nowhere", AsmError::UnknownLabel { line: 2, label: "nowhere".into() }),
+        ("a: CLS\na: RET", AsmError::DuplicateLabel { line: 2, label: "a".into() }),
+    ];
+    for (src, err) in cases {
+        assert_eq!(asm::assemble(src), Err(err), "{}", src);
+    }
+}
+
+#[test]
+fn is_key_pressed_follows_keypress() {
+    let mut emu = Emu::with_seed(0);
+    emu.keypress(0xA, true);
+    assert!(emu.is_key_pressed(0xA));
+    assert!(!emu.is_key_pressed(0xB));
+    assert!(!emu.is_key_pressed(20));
+    assert_eq!(emu.keys().iter().filter(|&&pressed| pressed).count(), 1);
+
+    emu.keypress(0xA, false);
+    assert!(!emu.is_key_pressed(0xA));
+}
+
+#[test]
+fn skip_key_uses_the_low_nibble_of_vx() {
+    // LD V0, 0x1A; SKP V0
+    let mut emu = Emu::from_rom(&[0x60, 0x1A, 0xE0, 0x9E]).unwrap();
+    emu.keypress(0xA, true);
+    emu.run_cycles(2);
+    assert_eq!(emu.pc(), 0x206);
+}
+
+// WAIT KEY into V0, then draw a random number of rows of the font, forever
+const WAIT_KEY_ROM: [u8; 12] = [0xF0, 0x0A, 0xC1, 0x0F, 0xF1, 0x29, 0xD2, 0x35, 0x72, 0x01, 0x12, 0x00];
+
+// run WAIT_KEY_ROM for 200 ticks, pressing and releasing keys as `inputs` says at the given ticks
+fn run_wait_key_rom(emu: &mut Emu, inputs: &[(usize, usize, bool)]) {
+    emu.load(&WAIT_KEY_ROM);
+    for tick in 0..200 {
+        for &(_, key, pressed) in inputs.iter().filter(|&&(at, _, _)| at == tick) {
+            emu.keypress(key, pressed);
+        }
+        emu.try_tick().unwrap();
+        if tick % 10 == 9 {
+            emu.tick_timers();
+        }
+    }
+}
+
+#[test]
+fn replaying_an_input_log_reproduces_the_run() {
+    // Both key waits see a press and a release several ticks apart, the last press and
+    // release happen between the same two ticks
+    let inputs = [(20, 0x5, true), (40, 0x5, false), (100, 0x9, true), (101, 0x9, false), (150, 0x2, true), (150, 0x2, false)];
+
+    let mut live = Emu::with_seed(7);
+    live.start_input_log();
+    run_wait_key_rom(&mut live, &inputs);
+    let log = live.take_input_log().unwrap();
+
+    let mut replayed = Emu::with_seed(7);
+    replayed.apply_input_log(&log);
+    run_wait_key_rom(&mut replayed, &[]);
+
+    assert_eq!(live.registers()[0], 0x9);
+    assert!(replayed == live);
+}
+
+#[test]
+fn arithmetic_on_vf_writes_the_flag_last() {
+    // Each case is VF, V1 and the flag left in VF, never the arithmetic result
+    let cases = [
+        // ADD VF, V1: 0x00 with a carry, 0x02 without one
+        ([0x8F, 0x14], 0xFF, 0x01, 1),
+        ([0x8F, 0x14], 0x01, 0x01, 0),
+        // SUB VF, V1: 0x02 without a borrow, 0xFE with one
+        ([0x8F, 0x15], 0x05, 0x03, 1),
+        ([0x8F, 0x15], 0x03, 0x05, 0),
+        // SUBN VF, V1: 0x02 without a borrow, 0xFE with one
+        ([0x8F, 0x17], 0x03, 0x05, 1),
+        ([0x8F, 0x17], 0x05, 0x03, 0),
+    ];
+    for (program, vf, v1, flag) in cases {
+        let mut emu = Emu::from_rom(&program).unwrap();
+        emu.v_reg[0xF] = vf;
+        emu.v_reg[1] = v1;
+        emu.try_tick().unwrap();
+        assert_eq!(emu.v_reg[0xF], flag, "{:02X}{:02X}", program[0], program[1]);
+    }
+}
+
+#[test]
+fn poke_ram_writes_in_range_and_rejects_the_rest() {
+    let mut emu = Emu::with_seed(0);
+    emu.poke_ram(0x300, 0xAB).unwrap();
+    emu.poke_ram(0xFFF, 0xCD).unwrap();
+    assert_eq!(emu.peek_ram(0x300), 0xAB);
+    assert_eq!(emu.peek_ram(0xFFF), 0xCD);
+
+    assert_eq!(emu.poke_ram(0x1000, 0xEF), Err(Chip8Error::AddressOutOfRange(0x1000)));
+    assert_eq!(emu.poke_ram(0xFFFF, 0xEF), Err(Chip8Error::AddressOutOfRange(0xFFFF)));
+    assert_eq!(emu.ram[0x1000], 0);
+
+    // Large memory opens up the rest of the address space
+    emu.set_large_memory(true);
+    emu.poke_ram(0xFFFF, 0xEF).unwrap();
+    assert_eq!(emu.peek_ram(0xFFFF), 0xEF);
+}
+
+#[test]
+fn set_v_reg_rejects_registers_past_vf() {
+    let mut emu = Emu::with_seed(0);
+    emu.set_v_reg(0xF, 0x2A).unwrap();
+    emu.set_i_reg(0x300);
+    assert_eq!(emu.v_reg(0xF), Some(0x2A));
+    assert_eq!(emu.i_reg(), 0x300);
+
+    assert_eq!(emu.set_v_reg(0x10, 0x2A), Err(Chip8Error::RegisterOutOfRange(0x10)));
+    assert_eq!(emu.v_reg(0x10), None);
+    assert_eq!(emu.registers(), &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x2A]);
+}
+
+#[test]
+fn exit_halts_until_reset_and_survives_a_save_state() {
+    // EXIT; LD V0, 1
+    let mut emu = Emu::from_rom(&[0x00, 0xFD, 0x60, 0x01]).unwrap();
+    assert_eq!(emu.run_cycles(5), 1);
+    assert!(emu.halted());
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.registers()[0], 0);
+
+    let snapshot = emu.save_state();
+    let mut restored = Emu::new();
+    restored.load_state(&snapshot).unwrap();
+    assert!(restored.halted());
+    restored.try_tick().unwrap();
+    assert_eq!(restored.pc(), 0x202);
+
+    emu.reset();
+    assert!(!emu.halted());
+}
+
+#[test]
+fn screen_dirty_is_set_by_draw_and_cls_and_cleared_on_read() {
+    // LD V0, 1; DRW V0, V0, 1; CLS
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x60, 0x01, 0xD0, 0x01, 0x00, 0xE0]);
+
+    // A new emulator starts dirty, reading the flag clears it
+    assert!(emu.take_screen_dirty());
+    assert!(!emu.take_screen_dirty());
+
+    emu.try_tick().unwrap();
+    assert!(!emu.take_screen_dirty());
+
+    emu.try_tick().unwrap();
+    assert!(emu.take_screen_dirty());
+    assert!(!emu.take_screen_dirty());
+
+    emu.try_tick().unwrap();
+    assert!(emu.take_screen_dirty());
+    assert!(!emu.take_screen_dirty());
+}
+
+#[test]
+fn rom_loads_and_runs_from_0x600() {
+    // LD V0, 5; JP 0x606; LD V0, 9; LD V1, 7
+    let rom = [0x60, 0x05, 0x16, 0x06, 0x60, 0x09, 0x61, 0x07];
+    let mut emu = Emu::with_seed(0);
+    emu.set_start_address(0x600).unwrap();
+    emu.try_load(&rom).unwrap();
+    assert_eq!(emu.pc(), 0x600);
+    assert_eq!(&emu.ram[0x600..0x608], &rom);
+    assert!(emu.ram[0x200..0x600].iter().all(|&byte| byte == 0));
+
+    emu.run_cycles(3);
+    assert_eq!(&emu.registers()[..2], &[5, 7]);
+    assert_eq!(emu.pc(), 0x608);
+
+    // The start address is kept across a reset
+    emu.reset();
+    assert_eq!(emu.pc(), 0x600);
+
+    // Less room is left for the ROM
+    assert_eq!(emu.try_load(&[0; 0xA00]), Ok(()));
+    assert_eq!(emu.try_load(&[0; 0xA01]), Err(Chip8Error::RomTooLarge(0xA01)));
+}
+
+#[test]
+fn ram_snapshot_holds_the_rom_at_the_start_address() {
+    let rom = include_bytes!("../../web/roms/PONG");
+    let mut emu = Emu::with_seed(0);
+    emu.load(rom);
+
+    let ram = emu.ram_snapshot();
+    assert_eq!(ram.len(), 0x1000);
+    assert_eq!(&ram[0x200..0x200 + rom.len()], rom);
+    assert_eq!(&ram[..5], &[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+    emu.set_large_memory(true);
+    assert_eq!(emu.ram_snapshot().len(), 0x10000);
+}
+
+#[test]
+fn clipped_draw_doesnt_light_the_next_row() {
+    // DRW V0, V1, 1 with a full row sprite right after it
+    let mut emu = EmuBuilder::default()
+        .quirks(Quirks { clip_sprite_body: true, ..Quirks::DEFAULT })
+        .build()
+        .unwrap();
+    emu.set_phosphor_decay(64);
+    emu.load(&[0xD0, 0x11, 0xFF]);
+    emu.set_v_reg(0, 60).unwrap();
+    emu.set_i_reg(0x202);
+    emu.try_tick().unwrap();
+
+    let intensity = emu.get_display_intensity();
+    assert_eq!(&intensity[60..64], &[255; 4]);
+    assert!(intensity[SCREEN_WIDTH..2 * SCREEN_WIDTH].iter().all(|&level| level == 0));
+}
+
+#[test]
+fn pixel_toggled_twice_in_a_frame_stays_lit() {
+    // LD V0, 0; LD F, V0; then the top row of the "0" is drawn, erased and drawn again
+    let rom = [0x60, 0x00, 0xF0, 0x29, 0xD0, 0x01, 0xD0, 0x01, 0xD0, 0x01];
+    let mut emu = Emu::with_seed(0);
+    emu.set_quirks(Quirks { accumulate_draws: true, ..Quirks::DEFAULT });
+    emu.load(&rom);
+
+    emu.run_cycles(3);
+    emu.tick_timers();
+    assert!(emu.get_display()[0]);
+
+    // Erasing shows up no earlier than the next frame, drawing it back keeps it lit
+    emu.run_cycles(1);
+    assert!(emu.get_display()[0]);
+    emu.run_cycles(1);
+    emu.tick_timers();
+    assert!(emu.get_display()[0]);
+    assert_eq!(emu.registers()[0xF], 0);
+
+    // Without the quirk the erased pixel is seen straight away, but still glows
+    let mut emu = Emu::with_seed(0);
+    emu.set_phosphor_decay(64);
+    emu.load(&rom);
+    emu.run_cycles(4);
+    assert!(!emu.get_display()[0]);
+    assert_eq!(emu.registers()[0xF], 1);
+    emu.tick_timers();
+    assert_eq!(&emu.get_display_intensity()[..5], &[191, 191, 191, 191, 0]);
+}
+
+#[test]
+fn display_is_sized_to_the_resolution() {
+    // HIGH; LOW
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x00, 0xFF, 0x00, 0xFE]);
+    assert_eq!(emu.resolution(), Resolution::Lores);
+
+    for expected in [Resolution::Hires, Resolution::Lores] {
+        emu.try_tick().unwrap();
+        assert_eq!(emu.resolution(), expected);
+
+        let (width, height) = emu.screen_dimensions();
+        assert_eq!(emu.get_display().len(), width * height);
+        assert_eq!(emu.get_display_bits().len() * 64, width * height);
+        assert_eq!(emu.get_display_intensity().len(), width * height);
+    }
+
+    // A reset goes back to the original display
+    emu.reset();
+    emu.load(&[0x00, 0xFF]);
+    emu.try_tick().unwrap();
+    assert_eq!(emu.resolution(), Resolution::Hires);
+    emu.reset();
+    assert_eq!(emu.resolution(), Resolution::Lores);
+}
+
+#[test]
+fn debug_and_display_summarize_the_cpu() {
+    let mut emu = Emu::with_seed(0);
+    assert!(format!("{:?}", emu).contains("PC=0x200"));
+
+    // LD V0, 5; LD VF, 0xAB; LD I, 0x300; CALL 0x208
+    emu.load(&[0x60, 0x05, 0x6F, 0xAB, 0xA3, 0x00, 0x22, 0x08]);
+    emu.run_cycles(4);
+    assert_eq!(
+        format!("{:?}", emu),
+        "Emu { PC=0x208 I=0x300 SP=1 DT=0 ST=0 V=[05 00 00 00 00 00 00 00 00 00 00 00 00 00 00 AB] }"
+    );
+    assert_eq!(
+        emu.to_string(),
+        "PC 0x208  I 0x300  SP 1  DT 0  ST 0\n\
+         V0 05  V1 00  V2 00  V3 00\n\
+         V4 00  V5 00  V6 00  V7 00\n\
+         V8 00  V9 00  VA 00  VB 00\n\
+         VC 00  VD 00  VE 00  VF AB"
+    );
+
+    // A soft reset goes back to 0x200 with the program still in memory
+    emu.soft_reset();
+    assert!(format!("{:?}", emu).starts_with("Emu { PC=0x200 I=0x000 SP=0 "));
+    assert_eq!(&emu.ram_snapshot()[0x200..0x202], &[0x60, 0x05]);
+}
+
+#[test]
+fn hw_cycles_sum_the_modeled_cost() {
+    // LD V0, 5; LD I, 0x300; LD [I], V2; DRW V0, V0, 1; CLS
+    let rom = [0x60, 0x05, 0xA3, 0x00, 0xF2, 0x55, 0xD0, 0x01, 0x00, 0xE0];
+    let mut emu = Emu::with_seed(0);
+    emu.load(&rom);
+    emu.run_cycles(5);
+    assert_eq!(emu.hw_cycles(), 0);
+
+    emu.reset();
+    emu.set_cycle_cost(Some(cycle_cost));
+    emu.load(&rom);
+    emu.run_cycles(5);
+    assert_eq!(emu.hw_cycles(), 6 + 12 + 56 + 90 + 3078);
+
+    // With display_wait, a DRAW waits out the rest of the frame
+    let mut emu = Emu::with_seed(0);
+    emu.set_quirks(Quirks { display_wait: true, ..Quirks::DEFAULT });
+    emu.set_cycle_cost(Some(cycle_cost));
+    emu.load(&rom[6..8]);
+    emu.try_tick().unwrap();
+    emu.tick_timers();
+    assert_eq!(emu.hw_cycles(), VIP_CYCLES_PER_FRAME as u64);
+}
+
+#[test]
+fn load_segment_places_each_segment_at_its_address() {
+    let mut emu = Emu::with_seed(0);
+    emu.load(&[0x12, 0x00]);
+    emu.load_segment(&[0xA1, 0xA2], 0x300).unwrap();
+    emu.load_segment(&[0xB1, 0xB2, 0xB3], 0xFFD).unwrap();
+
+    let ram = emu.ram_snapshot();
+    assert_eq!(&ram[0x200..0x202], &[0x12, 0x00]);
+    assert_eq!(&ram[0x300..0x302], &[0xA1, 0xA2]);
+    assert_eq!(&ram[0xFFD..0x1000], &[0xB1, 0xB2, 0xB3]);
+
+    // Overlapping segments are allowed, the last one wins
+    emu.load_segment(&[0xC1], 0x301).unwrap();
+    assert_eq!(&emu.ram_snapshot()[0x300..0x302], &[0xA1, 0xC1]);
+
+    // Segments that don't fit leave memory untouched
+    let before = emu.ram_snapshot().to_vec();
+    assert_eq!(emu.load_segment(&[1, 2, 3, 4], 0xFFD), Err(Chip8Error::RomTooLarge(4)));
+    assert_eq!(emu.load_segment(&[1], 0x1000), Err(Chip8Error::AddressOutOfRange(0x1000)));
+    assert_eq!(emu.ram_snapshot(), &before[..]);
+}
+
+// One test per instruction: a hand-encoded program, the minimal state it needs,
+// a single tick and the exact effect on the machine.
+
+// load `program` at the start address, prepare the machine with `setup`, then run one instruction
+fn run(program: &[u8], setup: impl FnOnce(&mut Emu)) -> Emu {
+    let mut emu = Emu::with_seed(0);
+    emu.load(program);
+    setup(&mut emu);
+    emu.try_tick().unwrap();
+    emu
+}
+
+// the state of pixel (x, y) of the first plane
+fn pixel(emu: &Emu, x: usize, y: usize) -> bool {
+    let (width, _) = emu.screen_dimensions();
+    emu.get_display()[y * width + x]
+}
+
+#[test]
+fn nop_only_advances_pc() {
+    let emu = run(&[0x00, 0x00], |_| ());
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.registers(), &[0; NUM_REGS]);
+    assert_eq!(emu.i_reg(), 0);
+    assert_eq!(emu.sp(), 0);
+}
+
+#[test]
+fn cls_clears_the_screen() {
+    let emu = run(&[0x00, 0xE0], |emu| emu.screen[0][0] = u64::MAX);
+    assert!(emu.get_display().iter().all(|&lit| !lit));
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn ret_pops_the_return_address() {
+    let emu = run(&[0x00, 0xEE], |emu| { emu.push(0x345).unwrap(); });
+    assert_eq!(emu.pc(), 0x345);
+    assert_eq!(emu.sp(), 0);
+}
+
+#[test]
+fn ret_with_an_empty_stack_underflows() {
+    let mut emu = Emu::new();
+    emu.load(&[0x00, 0xEE]);
+    assert_eq!(emu.try_tick(), Err(Chip8Error::StackUnderflow));
+}
+
+#[test]
+fn scroll_down_moves_rows_down() {
+    // In lores the amount is halved, so 00C4 scrolls 2 rows
+    let emu = run(&[0x00, 0xC4], |emu| emu.screen[0][0] = 1 << 63);
+    assert!(!pixel(&emu, 0, 0));
+    assert!(pixel(&emu, 0, 2));
+}
+
+#[test]
+fn scroll_right_moves_columns_right() {
+    let emu = run(&[0x00, 0xFB], |emu| emu.screen[0][0] = 1 << 63);
+    assert!(!pixel(&emu, 0, 0));
+    assert!(pixel(&emu, 2, 0));
+}
+
+#[test]
+fn scroll_left_moves_columns_left() {
+    let emu = run(&[0x00, 0xFC], |emu| emu.screen[0][0] = 1 << 60);
+    assert!(!pixel(&emu, 3, 0));
+    assert!(pixel(&emu, 1, 0));
+}
+
+#[test]
+fn exit_halts_the_program() {
+    let mut emu = run(&[0x00, 0xFD, 0x60, 0x01], |_| ());
+    assert!(emu.halted());
+
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.v_reg[0], 0);
+}
+
+#[test]
+fn high_and_low_switch_the_resolution() {
+    let mut emu = run(&[0x00, 0xFF, 0x00, 0xFE], |_| ());
+    assert_eq!(emu.resolution(), Resolution::Hires);
+    assert_eq!(emu.screen_dimensions(), (128, 64));
+
+    emu.try_tick().unwrap();
+    assert_eq!(emu.resolution(), Resolution::Lores);
+    assert_eq!(emu.screen_dimensions(), (64, 32));
+}
+
+#[test]
+fn jump_sets_pc() {
+    let emu = run(&[0x1A, 0xBC], |_| ());
+    assert_eq!(emu.pc(), 0xABC);
+}
+
+#[test]
+fn call_pushes_the_return_address() {
+    let emu = run(&[0x23, 0x45], |_| ());
+    assert_eq!(emu.pc(), 0x345);
+    assert_eq!(emu.sp(), 1);
+    assert_eq!(emu.stack(), &[0x202]);
+}
+
+#[test]
+fn call_with_a_full_stack_overflows() {
+    let mut emu = Emu::new();
+    emu.load(&[0x22, 0x00]);
+    for _ in 0..STACK_SIZE {
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(emu.try_tick(), Err(Chip8Error::StackOverflow));
+}
+
+// A ROM that CALLs itself, after `depth` ticks the stack is full
+fn recurse(depth: usize, policy: StackOverflowPolicy) -> Emu {
+    let mut emu = EmuBuilder::default().stack_size(depth).stack_overflow_policy(policy).build().unwrap();
+    emu.load(&[0x22, 0x00]);
+    for _ in 0..depth {
+        emu.try_tick().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn stack_size_moves_the_overflow() {
+    let mut emu = recurse(32, StackOverflowPolicy::Error);
+    assert_eq!(emu.stack_size(), 32);
+    assert_eq!(emu.sp(), 32);
+    assert_eq!(emu.try_tick(), Err(Chip8Error::StackOverflow));
+}
+
+#[test]
+fn ignored_overflow_skips_the_call() {
+    let mut emu = recurse(4, StackOverflowPolicy::Ignore);
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.sp(), 4);
+}
+
+#[test]
+fn wrapped_overflow_overwrites_the_oldest_address() {
+    let mut emu = EmuBuilder::default().stack_size(2).stack_overflow_policy(StackOverflowPolicy::Wrap).build().unwrap();
+    // CALL 0x202; CALL 0x204; CALL 0x206; RET
+    emu.load(&[0x22, 0x02, 0x22, 0x04, 0x22, 0x06, 0x00, 0xEE]);
+    for _ in 0..3 {
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(emu.sp(), 1);
+    assert_eq!(emu.stack(), &[0x206]);
+
+    // RET takes the newest address, then wraps around to the one left from the second CALL
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc(), 0x206);
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc(), 0x204);
+    assert_eq!(emu.sp(), 1);
+}
+
+#[test]
+fn stack_size_survives_a_save_state() {
+    let emu = recurse(20, StackOverflowPolicy::Error);
+    let mut restored = Emu::new();
+    restored.load_state(&emu.save_state()).unwrap();
+    assert_eq!(restored.stack_size(), 20);
+    assert_eq!(restored.stack(), emu.stack());
+}
+
+#[test]
+fn stack_size_is_clamped() {
+    let mut emu = Emu::new();
+    emu.set_stack_size(0);
+    assert_eq!(emu.stack_size(), 1);
+    emu.set_stack_size(MAX_STACK_SIZE + 1);
+    assert_eq!(emu.stack_size(), MAX_STACK_SIZE);
+
+    // Shrinking drops the newest addresses, growing again doesn't bring them back
+    let mut emu = recurse(4, StackOverflowPolicy::Error);
+    emu.set_stack_size(2);
+    assert_eq!(emu.stack(), &[0x202, 0x202]);
+    emu.set_stack_size(4);
+    assert_eq!(emu.sp(), 2);
+    assert!(emu.stack[2..].iter().all(|&addr| addr == 0));
+}
+
+#[test]
+fn skip_eq_imm() {
+    assert_eq!(run(&[0x31, 0x42], |emu| emu.v_reg[1] = 0x42).pc(), 0x204);
+    assert_eq!(run(&[0x31, 0x42], |emu| emu.v_reg[1] = 0x41).pc(), 0x202);
+}
+
+#[test]
+fn skip_ne_imm() {
+    assert_eq!(run(&[0x41, 0x42], |emu| emu.v_reg[1] = 0x41).pc(), 0x204);
+    assert_eq!(run(&[0x41, 0x42], |emu| emu.v_reg[1] = 0x42).pc(), 0x202);
+}
+
+#[test]
+fn skip_eq_reg() {
+    let equal = |emu: &mut Emu| {
+        emu.v_reg[1] = 7;
+        emu.v_reg[2] = 7;
+    };
+    assert_eq!(run(&[0x51, 0x20], equal).pc(), 0x204);
+    assert_eq!(run(&[0x51, 0x20], |emu| emu.v_reg[1] = 7).pc(), 0x202);
+}
+
+#[test]
+fn skip_ne_reg() {
+    let equal = |emu: &mut Emu| {
+        emu.v_reg[1] = 7;
+        emu.v_reg[2] = 7;
+    };
+    assert_eq!(run(&[0x91, 0x20], |emu| emu.v_reg[1] = 7).pc(), 0x204);
+    assert_eq!(run(&[0x91, 0x20], equal).pc(), 0x202);
+}
+
+#[test]
+fn set_reg() {
+    let emu = run(&[0x6A, 0x5C], |_| ());
+    assert_eq!(emu.v_reg[0xA], 0x5C);
+}
+
+#[test]
+fn add_imm_wraps_without_touching_vf() {
+    let emu = run(&[0x71, 0x02], |emu| emu.v_reg[1] = 0xFF);
+    assert_eq!(emu.v_reg[1], 0x01);
+    assert_eq!(emu.v_reg[0xF], 0);
+}
+
+#[test]
+fn move_copies_vy() {
+    let emu = run(&[0x81, 0x20], |emu| emu.v_reg[2] = 0x33);
+    assert_eq!(emu.v_reg[1], 0x33);
+    assert_eq!(emu.v_reg[2], 0x33);
+}
+
+#[test]
+fn or_and_xor() {
+    let setup = |emu: &mut Emu| {
+        emu.v_reg[1] = 0b1100;
+        emu.v_reg[2] = 0b1010;
+    };
+    assert_eq!(run(&[0x81, 0x21], setup).v_reg[1], 0b1110);
+    assert_eq!(run(&[0x81, 0x22], setup).v_reg[1], 0b1000);
+    assert_eq!(run(&[0x81, 0x23], setup).v_reg[1], 0b0110);
+}
+
+#[test]
+fn add_reg_sets_vf_on_carry() {
+    let carry = run(&[0x81, 0x24], |emu| {
+        emu.v_reg[1] = 0xF0;
+        emu.v_reg[2] = 0x20;
+    });
+    assert_eq!(carry.v_reg[1], 0x10);
+    assert_eq!(carry.v_reg[0xF], 1);
+
+    let no_carry = run(&[0x81, 0x24], |emu| {
+        emu.v_reg[1] = 0x10;
+        emu.v_reg[2] = 0x20;
+        emu.v_reg[0xF] = 1;
+    });
+    assert_eq!(no_carry.v_reg[1], 0x30);
+    assert_eq!(no_carry.v_reg[0xF], 0);
+}
+
+#[test]
+fn sub_reg_clears_vf_on_borrow() {
+    let borrow = run(&[0x81, 0x25], |emu| {
+        emu.v_reg[1] = 0x10;
+        emu.v_reg[2] = 0x20;
+    });
+    assert_eq!(borrow.v_reg[1], 0xF0);
+    assert_eq!(borrow.v_reg[0xF], 0);
+
+    let no_borrow = run(&[0x81, 0x25], |emu| {
+        emu.v_reg[1] = 0x20;
+        emu.v_reg[2] = 0x20;
+    });
+    assert_eq!(no_borrow.v_reg[1], 0x00);
+    assert_eq!(no_borrow.v_reg[0xF], 1);
+}
+
+#[test]
+fn subn_clears_vf_on_borrow() {
+    let borrow = run(&[0x81, 0x27], |emu| {
+        emu.v_reg[1] = 0x20;
+        emu.v_reg[2] = 0x10;
+    });
+    assert_eq!(borrow.v_reg[1], 0xF0);
+    assert_eq!(borrow.v_reg[0xF], 0);
+
+    let no_borrow = run(&[0x81, 0x27], |emu| {
+        emu.v_reg[1] = 0x10;
+        emu.v_reg[2] = 0x30;
+    });
+    assert_eq!(no_borrow.v_reg[1], 0x20);
+    assert_eq!(no_borrow.v_reg[0xF], 1);
+}
+
+#[test]
+fn shift_right_moves_the_lsb_into_vf() {
+    let emu = run(&[0x81, 0x26], |emu| emu.v_reg[1] = 0b101);
+    assert_eq!(emu.v_reg[1], 0b10);
+    assert_eq!(emu.v_reg[0xF], 1);
+}
+
+#[test]
+fn shift_left_moves_the_msb_into_vf() {
+    let emu = run(&[0x81, 0x2E], |emu| emu.v_reg[1] = 0x81);
+    assert_eq!(emu.v_reg[1], 0x02);
+    assert_eq!(emu.v_reg[0xF], 1);
+}
+
+#[test]
+fn set_index() {
+    let emu = run(&[0xA1, 0x23], |_| ());
+    assert_eq!(emu.i_reg(), 0x123);
+}
+
+#[test]
+fn jump_offset_adds_v0() {
+    let emu = run(&[0xB3, 0x00], |emu| emu.v_reg[0] = 0x10);
+    assert_eq!(emu.pc(), 0x310);
+}
+
+#[test]
+fn random_is_masked_by_nn() {
+    let emu = run(&[0xC1, 0x00], |emu| emu.v_reg[1] = 0xFF);
+    assert_eq!(emu.v_reg[1], 0);
+
+    let emu = run(&[0xC1, 0x0F], |_| ());
+    assert_eq!(emu.v_reg[1] & 0xF0, 0);
+}
+
+#[test]
+fn draw_xors_the_sprite_and_reports_collisions() {
+    // Font "0" starts with a 0xF0 row, drawn at (2, 1)
+    let setup = |emu: &mut Emu| {
+        emu.v_reg[1] = 2;
+        emu.v_reg[2] = 1;
+        emu.set_i_reg(FONTSET_START_ADDR);
+    };
+    let mut emu = run(&[0xD1, 0x21, 0x12, 0x00], setup);
+    assert!((2..6).all(|x| pixel(&emu, x, 1)));
+    assert!(!pixel(&emu, 6, 1));
+    assert_eq!(emu.v_reg[0xF], 0);
+
+    // Drawing it again turns the pixels back off and sets VF
+    emu.try_tick().unwrap();
+    emu.try_tick().unwrap();
+    assert!(emu.get_display().iter().all(|&lit| !lit));
+    assert_eq!(emu.v_reg[0xF], 1);
+}
+
+#[test]
+fn draw_wraps_at_the_right_edge() {
+    let emu = run(&[0xD1, 0x21], |emu| {
+        emu.v_reg[1] = 62;
+        emu.set_i_reg(FONTSET_START_ADDR);
+    });
+    assert!(pixel(&emu, 62, 0) && pixel(&emu, 63, 0));
+    assert!(pixel(&emu, 0, 0) && pixel(&emu, 1, 0));
+}
+
+#[test]
+fn draw_16x16_sprite() {
+    let emu = run(&[0xD0, 0x00], |emu| {
+        for addr in 0x300..0x320 {
+            emu.poke_ram(addr, 0xFF).unwrap();
+        }
+        emu.set_i_reg(0x300);
+    });
+    assert!((0..16).all(|y| (0..16).all(|x| pixel(&emu, x, y))));
+    assert!(!pixel(&emu, 16, 0) && !pixel(&emu, 0, 16));
+    assert_eq!(emu.v_reg[0xF], 0);
+}
+
+#[test]
+fn skip_key_pressed() {
+    let pressed = |emu: &mut Emu| {
+        emu.v_reg[1] = 0x5;
+        emu.keypress(0x5, true);
+    };
+    assert_eq!(run(&[0xE1, 0x9E], pressed).pc(), 0x204);
+    assert_eq!(run(&[0xE1, 0x9E], |emu| emu.v_reg[1] = 0x5).pc(), 0x202);
+}
+
+#[test]
+fn skip_key_released() {
+    let pressed = |emu: &mut Emu| {
+        emu.v_reg[1] = 0x5;
+        emu.keypress(0x5, true);
+    };
+    assert_eq!(run(&[0xE1, 0xA1], |emu| emu.v_reg[1] = 0x5).pc(), 0x204);
+    assert_eq!(run(&[0xE1, 0xA1], pressed).pc(), 0x202);
+}
+
+#[test]
+fn skip_key_only_uses_the_low_nibble() {
+    let emu = run(&[0xE1, 0x9E], |emu| {
+        emu.v_reg[1] = 0x35;
+        emu.keypress(0x5, true);
+    });
+    assert_eq!(emu.pc(), 0x204);
+}
+
+#[test]
+fn get_delay() {
+    let emu = run(&[0xF1, 0x07], |emu| emu.dt = 42);
+    assert_eq!(emu.v_reg[1], 42);
+}
+
+#[test]
+fn wait_key_blocks_until_a_key_is_released() {
+    let mut emu = run(&[0xF1, 0x0A], |_| ());
+    assert_eq!(emu.pc(), 0x200);
+
+    // Pressing isn't enough, the key has to be released
+    emu.keypress(0x7, true);
+    emu.try_tick().unwrap();
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc(), 0x200);
+    assert_eq!(emu.v_reg[1], 0);
+
+    emu.keypress(0x7, false);
+    emu.try_tick().unwrap();
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.v_reg[1], 0x7);
+}
+
+#[test]
+fn set_delay_and_sound() {
+    let setup = |emu: &mut Emu| emu.v_reg[1] = 30;
+    assert_eq!(run(&[0xF1, 0x15], setup).delay_timer(), 30);
+    assert_eq!(run(&[0xF1, 0x18], setup).sound_timer(), 30);
+}
+
+#[test]
+fn add_index_wraps_at_12_bits() {
+    let emu = run(&[0xF1, 0x1E], |emu| {
+        emu.v_reg[1] = 0x02;
+        emu.set_i_reg(0xFFF);
+    });
+    assert_eq!(emu.i_reg(), 0x001);
+    assert_eq!(emu.v_reg[0xF], 0);
+}
+
+#[test]
+fn font_char_points_at_the_digit() {
+    let emu = run(&[0xF1, 0x29], |emu| emu.v_reg[1] = 0xA);
+    assert_eq!(emu.i_reg(), FONTSET_START_ADDR + 0xA * 5);
+}
+
+#[test]
+fn big_font_char_points_at_the_digit() {
+    let emu = run(&[0xF1, 0x30], |emu| emu.v_reg[1] = 0x3);
+    assert_eq!(emu.i_reg(), BIG_FONTSET_START_ADDR + 0x3 * 10);
+}
+
+#[test]
+fn bcd_stores_the_decimal_digits() {
+    let emu = run(&[0xF1, 0x33], |emu| {
+        emu.v_reg[1] = 254;
+        emu.set_i_reg(0x300);
+    });
+    assert_eq!(&emu.ram_snapshot()[0x300..0x303], &[2, 5, 4]);
+    assert_eq!(emu.i_reg(), 0x300);
+}
+
+#[test]
+fn store_regs_writes_v0_to_vx() {
+    let emu = run(&[0xF2, 0x55], |emu| {
+        for idx in 0..4 {
+            emu.v_reg[idx] = idx as u8 + 1;
+        }
+        emu.set_i_reg(0x300);
+    });
+    assert_eq!(&emu.ram_snapshot()[0x300..0x304], &[1, 2, 3, 0]);
+    assert_eq!(emu.i_reg(), 0x300);
+}
+
+#[test]
+fn load_regs_reads_v0_to_vx() {
+    let emu = run(&[0xF2, 0x65], |emu| {
+        emu.load_segment(&[1, 2, 3, 4], 0x300).unwrap();
+        emu.set_i_reg(0x300);
+    });
+    assert_eq!(&emu.registers()[..4], &[1, 2, 3, 0]);
+    assert_eq!(emu.i_reg(), 0x300);
+}
+
+#[test]
+fn store_and_load_flags() {
+    let emu = run(&[0xF1, 0x75], |emu| {
+        emu.v_reg[0] = 9;
+        emu.v_reg[1] = 8;
+        emu.v_reg[2] = 7;
+    });
+    assert_eq!(emu.get_flags(), [9, 8, 0, 0, 0, 0, 0, 0]);
+
+    let emu = run(&[0xF1, 0x85], |emu| emu.set_flags([4, 5, 6, 0, 0, 0, 0, 0]));
+    assert_eq!(&emu.registers()[..3], &[4, 5, 0]);
+}
+
+#[test]
+fn load_long_index_skips_the_address() {
+    let emu = run(&[0xF0, 0x00, 0x12, 0x34], |_| ());
+    assert_eq!(emu.i_reg(), 0x1234);
+    assert_eq!(emu.pc(), 0x204);
+}
+
+#[test]
+fn load_audio_copies_16_bytes() {
+    let pattern: Vec<u8> = (1..=16).collect();
+    let emu = run(&[0xF0, 0x02], |emu| {
+        emu.load_segment(&pattern, 0x300).unwrap();
+        emu.set_i_reg(0x300);
+    });
+    assert_eq!(&emu.get_audio_buffer()[..], &pattern[..]);
+}
+
+#[test]
+fn select_planes_masks_to_two_planes() {
+    let emu = run(&[0xFF, 0x01], |_| ());
+    assert_eq!(emu.plane_mask, 0b11);
+}
+
+#[test]
+fn set_pitch() {
+    let emu = run(&[0xF1, 0x3A], |emu| emu.v_reg[1] = 112);
+    assert_eq!(emu.get_audio_pitch(), 112);
+}
+
+#[test]
+fn unknown_opcode_is_an_error() {
+    let mut emu = Emu::new();
+    emu.load(&[0x80, 0x08]);
+    assert_eq!(emu.try_tick(), Err(Chip8Error::UnknownOpcode { addr: 0x200, op: 0x8008 }));
+}
+
+#[test]
+fn unknown_opcode_reports_its_address() {
+    // JP 0x2A4, with 5FE1 at 0x2A4
+    let mut emu = Emu::new();
+    emu.load(&[0x12, 0xA4]);
+    emu.load_segment(&[0x5F, 0xE1], 0x2A4).unwrap();
+
+    emu.try_tick().unwrap();
+    let err = emu.try_tick().unwrap_err();
+    assert_eq!(err, Chip8Error::UnknownOpcode { addr: 0x2A4, op: 0x5FE1 });
+    assert_eq!(err.to_string(), "unknown opcode 0x5FE1 at 0x2A4");
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn write_screen_pbm_packs_rows_msb_first() {
+    let mut emu = Emu::new();
+    emu.screen[0][0] = 0x8000_0000_0000_0001;
+    emu.screen[0][31] = u64::MAX;
+
+    let path = std::env::temp_dir().join(format!("chip8-screen-{}.pbm", std::process::id()));
+    emu.write_screen_pbm(&path).unwrap();
+    let pbm = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let header = b"P4\n64 32\n";
+    assert_eq!(&pbm[..header.len()], header);
+
+    let pixels = &pbm[header.len()..];
+    assert_eq!(pixels.len(), 32 * 8);
+    assert_eq!(&pixels[..8], &[0x80, 0, 0, 0, 0, 0, 0, 0x01]);
+    assert!(pixels[8..31 * 8].iter().all(|&byte| byte == 0));
+    assert!(pixels[31 * 8..].iter().all(|&byte| byte == 0xFF));
+}
+
+#[test]
+fn step_over_runs_through_a_call() {
+    // CALL 0x206; LD V1, 1; JP 0x204; 0x206: LD V0, 5; RET
+    let program = [0x22, 0x06, 0x61, 0x01, 0x12, 0x04, 0x60, 0x05, 0x00, 0xEE];
+    let mut emu = Emu::new();
+    emu.load(&program);
+
+    assert_eq!(emu.step_over(100), Ok(StopReason::Stepped));
+    assert_eq!(emu.pc(), 0x202);
+    assert_eq!(emu.sp(), 0);
+    assert_eq!(emu.registers()[0], 5);
+    assert_eq!(emu.registers()[1], 0);
+
+    // Anything else is a single step
+    assert_eq!(emu.step_over(100), Ok(StopReason::Stepped));
+    assert_eq!(emu.pc(), 0x204);
+    assert_eq!(emu.registers()[1], 1);
+}
+
+#[test]
+fn step_over_a_call_on_a_full_stack() {
+    // CALL 0x204; CALL 0x208; 0x208: CALL 0x20E; LD V1, 1; JP 0x20C; 0x20E: LD V0, 5; RET
+    let program = [
+        0x22, 0x04, 0x00, 0x00, 0x22, 0x08, 0x00, 0x00, 0x22, 0x0E, 0x61, 0x01, 0x12, 0x0C, 0x60, 0x05, 0x00, 0xEE,
+    ];
+
+    // The CALL wraps SP around to the bottom of the stack, the step still runs the whole subroutine
+    let mut emu = Emu::with_seed(0);
+    emu.set_stack_size(2);
+    emu.set_stack_overflow_policy(StackOverflowPolicy::Wrap);
+    emu.load(&program);
+    emu.run_cycles(2);
+    assert_eq!((emu.pc(), emu.sp()), (0x208, 2));
+    assert_eq!(emu.step_over(100), Ok(StopReason::Stepped));
+    assert_eq!((emu.pc(), emu.sp()), (0x20A, 0));
+    assert_eq!(emu.registers()[0], 5);
+    assert_eq!(emu.registers()[1], 0);
+
+    // A CALL the stack has no room for is skipped, so it is a single step
+    let mut emu = Emu::with_seed(0);
+    emu.set_stack_size(2);
+    emu.set_stack_overflow_policy(StackOverflowPolicy::Ignore);
+    emu.load(&program);
+    emu.run_cycles(2);
+    assert_eq!(emu.step_over(100), Ok(StopReason::Stepped));
+    assert_eq!((emu.pc(), emu.sp()), (0x20A, 2));
+    assert_eq!(emu.registers()[0], 0);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn run_frame_throttled_runs_the_requested_ticks() {
+    use std::time::{Duration, Instant};
+
+    // JP 0x200
+    let mut emu = Emu::new();
+    emu.load(&[0x12, 0x00]);
+
+    // A frame that started long ago is behind schedule, so this doesn't sleep
+    let frame_start = Instant::now() - Duration::from_secs(1);
+    emu.run_frame_throttled(7, frame_start, Duration::from_millis(16)).unwrap();
+    assert_eq!(emu.cycle_count(), 7);
+    assert_eq!(emu.frame_count(), 1);
+
+    let frame_start = Instant::now();
+    emu.run_frame_throttled(7, frame_start, Duration::from_millis(5)).unwrap();
+    assert!(frame_start.elapsed() >= Duration::from_millis(5));
+    assert_eq!(emu.cycle_count(), 14);
+}
+
+#[test]
+fn shifting_vf_keeps_the_shifted_out_bit() {
+    // The shift result is overwritten, the flag wins
+    let emu = run(&[0x8F, 0x06], |emu| emu.v_reg[0xF] = 0b10);
+    assert_eq!(emu.v_reg[0xF], 0);
+    let emu = run(&[0x8F, 0x06], |emu| emu.v_reg[0xF] = 0b11);
+    assert_eq!(emu.v_reg[0xF], 1);
+
+    let emu = run(&[0x8F, 0x0E], |emu| emu.v_reg[0xF] = 0x40);
+    assert_eq!(emu.v_reg[0xF], 0);
+    let emu = run(&[0x8F, 0x0E], |emu| emu.v_reg[0xF] = 0xC0);
+    assert_eq!(emu.v_reg[0xF], 1);
+}
+
+#[test]
+fn screen_delta_lists_changed_pixels() {
+    let mut emu = Emu::new();
+    emu.screen[0][0] = 1 << 63;
+    let prev = emu.get_display();
+
+    // Turn (0, 0) off and (3, 1) on
+    emu.screen[0][0] = 0;
+    emu.screen[0][1] = 1 << 60;
+    assert_eq!(emu.screen_delta(&prev), vec![(0, false), (64 + 3, true)]);
+    assert!(emu.screen_delta(&emu.get_display()).is_empty());
+}
+
+#[test]
+fn pressed_keys_lists_held_keys() {
+    let mut emu = Emu::new();
+    emu.keypress(9, true);
+    emu.keypress(3, true);
+    emu.keypress(5, true);
+    emu.keypress(5, false);
+    assert_eq!(emu.pressed_keys().collect::<Vec<_>>(), vec![3, 9]);
+}
+
+#[test]
+fn wait_key_sets_waiting_for_key_until_the_key_is_released() {
+    let mut emu = run(&[0xF3, 0x0A], |_| ());
+    assert_eq!(emu.waiting_for_key(), Some(3));
+
+    // Waiting doesn't execute anything
+    emu.try_tick().unwrap();
+    assert_eq!(emu.cycle_count(), 1);
+
+    emu.keypress(0xB, true);
+    emu.try_tick().unwrap();
+    assert_eq!(emu.waiting_for_key(), Some(3));
+
+    emu.keypress(0xB, false);
+    emu.try_tick().unwrap();
+    assert_eq!(emu.waiting_for_key(), None);
+    assert_eq!(emu.registers()[3], 0xB);
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn key_wait_completes_during_a_replay() {
+    let log = InputLog {
+        events: vec![
+            InputEvent { tick: 5, key: 0xA, pressed: true },
+            InputEvent { tick: 8, key: 0xA, pressed: false },
+        ],
+    };
+
+    let mut emu = Emu::new();
+    emu.load(&[0xF3, 0x0A]);
+    emu.apply_input_log(&log);
+
+    for _ in 0..8 {
+        emu.try_tick().unwrap();
+    }
+    assert_eq!(emu.waiting_for_key(), Some(3));
+    assert!(emu.is_key_pressed(0xA));
+
+    // The release lands before the ninth tick, which completes the wait
+    emu.try_tick().unwrap();
+    assert_eq!(emu.waiting_for_key(), None);
+    assert_eq!(emu.registers()[3], 0xA);
+    assert_eq!(emu.pc(), 0x202);
+}
+
+#[test]
+fn loaded_rom_hash_is_the_crc_of_the_rom() {
+    let mut emu = Emu::new();
+    assert_eq!(emu.loaded_rom_hash(), None);
+
+    emu.load(include_bytes!("../../web/roms/PONG"));
+    assert_eq!(emu.loaded_rom_hash(), Some(0x7D75_A857));
+
+    emu.reset();
+    assert_eq!(emu.loaded_rom_hash(), None);
+}
+
+#[test]
+fn soft_reset_keeps_the_rom() {
+    // LD V0, 5; LD I, 0x300; LD [I], V0; JP 0x206
+    let rom = [0x60, 0x05, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06];
+    let mut emu = Emu::new();
+    emu.load(&rom);
+    emu.run_cycles(4);
+    assert_eq!(emu.peek_ram(0x300), 5);
+
+    emu.soft_reset();
+    assert_eq!(emu.pc(), 0x200);
+    assert_eq!(emu.registers()[0], 0);
+    assert_eq!(emu.i_reg(), 0);
+    assert_eq!(&emu.ram_snapshot()[0x200..0x208], &rom);
+    assert_eq!(emu.peek_ram(0x300), 0);
+    assert_eq!(emu.loaded_rom_hash(), Some(crc32::crc32(&rom)));
+}
+
+#[test]
+fn soft_reset_after_moving_the_start_address_stays_in_memory() {
+    // A ROM filling the 64KB address space, then the start address moves past its beginning
+    let mut emu = Emu::new();
+    emu.set_large_memory(true);
+    emu.try_load(&[0xAB; LARGE_RAM_SIZE - 0x200]).unwrap();
+    emu.set_start_address(0x800).unwrap();
+
+    emu.soft_reset();
+    assert_eq!(emu.pc(), 0x800);
+    assert!(emu.ram[0x200..0x800].iter().all(|&byte| byte == 0));
+    assert!(emu.ram[0x800..].iter().all(|&byte| byte == 0xAB));
+}
+
+#[test]
+fn fonts_are_installed_in_ram() {
+    let emu = Emu::new();
+    let ram = emu.ram_snapshot();
+
+    let start = FONTSET_START_ADDR as usize;
+    assert_eq!(&ram[start..start + 5], &[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+    assert_eq!(&ram[start..start + FONTSET_SIZE], &FONTSET);
+
+    let big_start = BIG_FONTSET_START_ADDR as usize;
+    assert_eq!(&ram[big_start..big_start + BIG_FONTSET_SIZE], &BIG_FONTSET);
+}
+
+#[test]
+fn sprite_body_clips_with_a_wrapped_origin() {
+    assert_eq!(draw_at_right_edge(62, true, true), vec![62, 63]);
+    assert_eq!(draw_at_right_edge(66, true, true), vec![2, 3, 4, 5]);
+}
+
+#[test]
+fn sprite_body_wraps_with_an_unwrapped_origin() {
+    assert_eq!(draw_at_right_edge(62, false, false), vec![0, 1, 62, 63]);
+    assert_eq!(draw_at_right_edge(66, false, false), vec![2, 3, 4, 5]);
+}
+
+#[test]
+fn clipped_sprite_with_an_unwrapped_origin_vanishes() {
+    assert_eq!(draw_at_right_edge(62, false, true), vec![62, 63]);
+    assert_eq!(draw_at_right_edge(66, false, true), Vec::<usize>::new());
+}
+
+#[test]
+fn set_timers_directly() {
+    let mut emu = Emu::new();
+    emu.set_delay_timer(30);
+    emu.set_sound_timer(2);
+    emu.tick_timers();
+    assert_eq!(emu.delay_timer(), 29);
+    assert_eq!(emu.sound_timer(), 1);
+    assert!(emu.is_beeping());
+}
+
+#[test]
+fn listing_marks_undecodable_words_as_data() {
+    let mut emu = Emu::new();
+    // LD VA, 0x02; LD I, 0x0208; JP 0x206; a sprite row; a lone byte
+    emu.load(&[0x6A, 0x02, 0xF0, 0x00, 0x02, 0x08, 0x12, 0x06, 0xFF, 0xFF, 0x80]);
+
+    let listing = emu.listing();
+    let lines: Vec<&str> = listing.lines().filter(|line| !line.starts_with(';')).collect();
+    assert_eq!(lines, vec![
+        "0x200: 6A02    LD VA, 0x02",
+        "0x202: F000 0208 LD I, 0x0208",
+        "0x206: 1206    JP 0x206",
+        "0x208: FFFF    DB 0xFF, 0xFF",
+        "0x20A: 80      DB 0x80",
+    ]);
+}
+
+#[test]
+fn listing_stops_at_the_end_of_memory() {
+    let mut emu = Emu::new();
+    emu.set_large_memory(true);
+    emu.try_load(&[0x00; LARGE_RAM_SIZE - 0x200]).unwrap();
+    emu.set_start_address(0xFFE).unwrap();
+
+    // The ROM is listed from its new start, up to the last word in memory
+    let listing = emu.listing();
+    let lines: Vec<&str> = listing.lines().filter(|line| !line.starts_with(';')).collect();
+    assert_eq!(lines.len(), (LARGE_RAM_SIZE - 0xFFE) / 2);
+}
@@ -0,0 +1,84 @@
+use crate::Emu;
+
+/// CycleCost returns how many machine cycles the instruction `op` takes, see `Emu::set_cycle_cost`.
+pub type CycleCost = fn(u16) -> u32;
+
+/// Machine cycles the COSMAC VIP runs per 60Hz frame, its 1.76MHz clock takes 8 clocks per cycle.
+pub const VIP_CYCLES_PER_FRAME: u32 = 3668;
+
+/// Approximate COSMAC VIP timing of `op`, in machine cycles.
+///
+/// Conditional skips are counted as not taken and FX33 at its cheapest, so this is a lower
+/// bound for those. DXYN grows with the sprite height, and waiting for the vertical blank
+/// with the `display_wait` quirk is added separately, see `Emu::hw_cycles`.
+pub fn cycle_cost(op: u16) -> u32 {
+    let x = ((op & 0x0F00) >> 8) as u32;
+    let n = (op & 0x000F) as u32;
+
+    match op >> 12 {
+        0x0 => match op {
+            0x00E0 => 3078,
+            _ => 10,
+        },
+        0x1 => 12,
+        0x2 => 26,
+        0x3 | 0x4 => 10,
+        0x5 | 0x9 => 14,
+        0x6 => 6,
+        0x7 => 10,
+        0x8 => 44,
+        0xA => 12,
+        0xB => 22,
+        0xC => 36,
+        0xD => 22 + 68 * n,
+        0xE => 14,
+        _ => match op & 0xFF {
+            0x0A => 19,
+            0x1E | 0x29 => 16,
+            0x33 => 80,
+            0x55 | 0x65 => 14 + 14 * (x + 1),
+            _ => 10,
+        },
+    }
+}
+
+impl Emu {
+    /// Counts the cost of every executed instruction in `hw_cycles`, for frontends that pace
+    /// the CPU by real instruction timings rather than a flat `ticks_per_frame`.
+    ///
+    /// `cycle_cost` models the COSMAC VIP. `None`, the default, turns the count off. This
+    /// only measures, the instructions behave the same either way. It is a machine setting,
+    /// so it is kept across `reset`.
+    pub fn set_cycle_cost(&mut self, cost: Option<CycleCost>)
+    {
+        self.cycle_cost = cost;
+    }
+
+    /// Returns the machine cycles executed since the last reset, according to `set_cycle_cost`.
+    ///
+    /// A DRAW waiting for the vertical blank (the `display_wait` quirk) also costs the rest of
+    /// the frame, up to `VIP_CYCLES_PER_FRAME` since the previous one. Always 0 without a
+    /// cost model. It isn't part of save states.
+    pub fn hw_cycles(&self) -> u64
+    {
+        self.hw_cycles
+    }
+
+    // count the cost of an executed instruction
+    pub(crate) fn add_cycle_cost(&mut self, op: u16)
+    {
+        if let Some(cost) = self.cycle_cost {
+            self.hw_cycles += cost(op) as u64;
+        }
+    }
+
+    // at the vertical blank, a waiting DRAW takes the rest of the frame
+    pub(crate) fn end_frame_cycles(&mut self)
+    {
+        if self.cycle_cost.is_some() && self.waiting_for_vblank {
+            let frame_end = self.frame_start_hw_cycles + VIP_CYCLES_PER_FRAME as u64;
+            self.hw_cycles = self.hw_cycles.max(frame_end);
+        }
+        self.frame_start_hw_cycles = self.hw_cycles;
+    }
+}
@@ -0,0 +1,76 @@
+// Runs the games bundled in web/roms unattended and compares the final screen against
+// a stored snapshot, see tests/screens/README.md.
+//
+// These are regression tests: the snapshots record what this emulator drew when they
+// were blessed, they aren't checked against another interpreter.
+
+use chip8_core::{EmuBuilder, Quirks};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Set to write the snapshots from the current screens instead of comparing against them
+const BLESS_VAR: &str = "CHIP8_BLESS";
+
+struct Case {
+    rom: &'static str,
+    quirks: Quirks,
+    frames: usize,
+}
+
+// The games run from seed 0, far enough to be past their title screens
+const CASES: &[Case] = &[
+    Case { rom: "BLITZ", quirks: Quirks { clip_sprite_body: true, ..Quirks::DEFAULT }, frames: 300 },
+    Case { rom: "BRIX", quirks: Quirks::DEFAULT, frames: 300 },
+    Case { rom: "INVADERS", quirks: Quirks::DEFAULT, frames: 300 },
+    Case { rom: "MAZE", quirks: Quirks::DEFAULT, frames: 120 },
+    Case { rom: "PONG", quirks: Quirks::DEFAULT, frames: 300 },
+];
+
+fn roms_dir() -> PathBuf
+{
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join("web").join("roms")
+}
+
+fn screens_dir() -> PathBuf
+{
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("screens")
+}
+
+// The screen packed 8 pixels per byte, the most significant bit is the leftmost pixel
+fn run(case: &Case, rom: &[u8]) -> Vec<u8>
+{
+    let mut emu = EmuBuilder::default().quirks(case.quirks).seed(0).build().unwrap();
+    emu.try_load(rom).unwrap();
+
+    for _ in 0..case.frames {
+        emu.run_frame(emu.ticks_per_frame())
+            .unwrap_or_else(|err| panic!("{} hit an error: {}", case.rom, err));
+    }
+
+    emu.get_display_bits()
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect()
+}
+
+#[test]
+fn bundled_games()
+{
+    let bless = env::var_os(BLESS_VAR).is_some();
+
+    for case in CASES {
+        let rom = fs::read(roms_dir().join(case.rom)).unwrap();
+        let screen = run(case, &rom);
+        let snapshot_path = screens_dir().join(case.rom).with_extension("bits");
+
+        if bless {
+            fs::write(&snapshot_path, &screen).unwrap();
+            continue;
+        }
+
+        let expected = fs::read(&snapshot_path)
+            .unwrap_or_else(|_| panic!("no snapshot for {}, run with {}=1 to create it", case.rom, BLESS_VAR));
+        assert!(screen == expected, "{} doesn't match its snapshot", case.rom);
+    }
+}
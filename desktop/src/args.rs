@@ -0,0 +1,151 @@
+use crate::config::Config;
+use chip8_core::{Quirks, DEFAULT_TARGET_IPS};
+use sdl2::pixels::Color;
+
+pub const DEFAULT_SCALE: u32 = 15;
+pub const DEFAULT_FG: Color = Color::RGB(255, 255, 255);
+pub const DEFAULT_BG: Color = Color::RGB(0, 0, 0);
+pub const DEFAULT_BEEP_COLOR: Color = Color::RGB(255, 0, 0);
+
+const MIN_SCALE: u32 = 1;
+const MAX_SCALE: u32 = 50;
+// One instruction per frame up to a thousand
+pub const MIN_TARGET_IPS: u32 = 60;
+pub const MAX_TARGET_IPS: u32 = 60000;
+
+pub const USAGE: &str = "\
+Usage: cargo run path/to/game [options]
+
+Options:
+    --scale <n>     Size of a CHIP-8 pixel in window pixels (1-50, default 15)
+    --speed <n>     Instructions executed per second (60-60000, default 600)
+    --fg <RRGGBB>   Color of set pixels as a hex triplet, e.g. ffb000 for amber (default ffffff)
+    --bg <RRGGBB>   Color of unset pixels as a hex triplet (default 000000)
+    --visual-beep   Flash the window border while the beep plays
+    --beep-color <RRGGBB>
+                    Color of the border flash (default ff0000)
+
+Settings are also read from chip8.toml in the working directory, and from a file next to
+the game with the same name and a .toml extension, e.g. games/PONG.toml for games/PONG.ch8.
+The game's file overrides chip8.toml, and the options above override both.";
+
+/// Options holds the settings the emulator runs with, from the config files and the command line.
+pub struct Options {
+    pub rom_path: String,
+    pub scale: u32,
+    pub target_ips: u32,
+    pub fg: Color,
+    pub bg: Color,
+    pub visual_beep: bool,
+    pub beep_color: Color,
+    pub quirks: Quirks,
+}
+
+/// Parses the arguments following the program name into the game's path and the settings
+/// they override, see `resolve_options`.
+///
+/// Returns a message describing the first invalid argument on failure.
+pub fn parse_args(args: &[String]) -> Result<(String, Config), String>
+{
+    let mut rom_path = None;
+    let mut config = Config::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--scale" => {
+                config.scale = Some(parse_value(arg, iter.next(), MIN_SCALE, MAX_SCALE)?);
+            },
+            "--speed" => {
+                config.speed = Some(parse_value(arg, iter.next(), MIN_TARGET_IPS, MAX_TARGET_IPS)?);
+            },
+            "--fg" => {
+                config.fg = Some(flag_value(arg, iter.next())?);
+            },
+            "--bg" => {
+                config.bg = Some(flag_value(arg, iter.next())?);
+            },
+            "--visual-beep" => {
+                config.visual_beep = Some(true);
+            },
+            "--beep-color" => {
+                config.beep_color = Some(flag_value(arg, iter.next())?);
+            },
+            flag if flag.starts_with("--") => {
+                return Err(format!("Unknown option {}", flag));
+            },
+            path => {
+                if rom_path.is_some() {
+                    return Err(format!("Unexpected argument {}", path));
+                }
+                rom_path = Some(path.to_string());
+            },
+        }
+    }
+
+    let rom_path = rom_path.ok_or("Missing path to game")?;
+
+    Ok((rom_path, config))
+}
+
+/// Fills in the settings missing from `config` with the built-in defaults.
+///
+/// Returns a message describing the first out of range setting on failure.
+pub fn resolve_options(rom_path: String, config: &Config) -> Result<Options, String>
+{
+    let scale = config_value("scale", config.scale, DEFAULT_SCALE, MIN_SCALE, MAX_SCALE)?;
+    let target_ips = config_value("speed", config.speed, DEFAULT_TARGET_IPS, MIN_TARGET_IPS, MAX_TARGET_IPS)?;
+    let fg = parse_color("fg", config.fg.as_deref(), DEFAULT_FG);
+    let bg = parse_color("bg", config.bg.as_deref(), DEFAULT_BG);
+    let visual_beep = config.visual_beep.unwrap_or(false);
+    let beep_color = parse_color("beep color", config.beep_color.as_deref(), DEFAULT_BEEP_COLOR);
+    let quirks = config.quirks.apply(Quirks::default());
+
+    Ok(Options { rom_path, scale, target_ips, fg, bg, visual_beep, beep_color, quirks })
+}
+
+// return the value following a flag
+fn flag_value(flag: &str, value: Option<&String>) -> Result<String, String>
+{
+    value.cloned().ok_or(format!("Missing value for {}", flag))
+}
+
+// parse the value following a flag and check it's within min..=max
+fn parse_value<T>(flag: &str, value: Option<&String>, min: T, max: T) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+{
+    let value = value.ok_or(format!("Missing value for {}", flag))?;
+
+    match value.parse::<T>() {
+        Ok(n) if n >= min && n <= max => Ok(n),
+        _ => Err(format!("Invalid value for {}: {} (expected {}-{})", flag, value, min, max)),
+    }
+}
+
+// check a setting is within min..=max, or use the default when it's missing
+fn config_value<T>(name: &str, value: Option<T>, default: T, min: T, max: T) -> Result<T, String>
+where
+    T: PartialOrd + std::fmt::Display,
+{
+    match value {
+        Some(n) if n < min || n > max => Err(format!("Invalid value for {}: {} (expected {}-{})", name, n, min, max)),
+        Some(n) => Ok(n),
+        None => Ok(default),
+    }
+}
+
+// parse an RRGGBB color, a missing color uses the default and a malformed one only warns and uses it too
+fn parse_color(name: &str, value: Option<&str>, default: Color) -> Color
+{
+    let Some(value) = value else { return default };
+    let hex = value.strip_prefix('#').unwrap_or(value);
+
+    match u32::from_str_radix(hex, 16) {
+        Ok(rgb) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8),
+        _ => {
+            eprintln!("Warning: invalid color for {}: {} (expected RRGGBB), using the default", name, value);
+            default
+        },
+    }
+}
@@ -0,0 +1,44 @@
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+// Pitch and loudness of the beep
+const BEEP_FREQUENCY: f32 = 440.0;
+const BEEP_VOLUME: f32 = 0.25;
+
+/// SquareWave generates the beep, SDL calls it from the audio thread whenever it needs samples.
+pub struct SquareWave {
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32])
+    {
+        // High for the first half of every period, low for the second half
+        for sample in out.iter_mut() {
+            *sample = if self.phase <= 0.5 { self.volume } else { -self.volume };
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+/// Opens a paused playback device producing the beep.
+///
+/// The device is opened once and then resumed and paused as the sound timer starts and stops.
+pub fn open_beep(audio_subsystem: &AudioSubsystem) -> Result<AudioDevice<SquareWave>, String>
+{
+    let desired_spec = AudioSpecDesired {
+        freq: Some(44100),
+        channels: Some(1),
+        samples: None,
+    };
+
+    audio_subsystem.open_playback(None, &desired_spec, |spec| SquareWave {
+        phase_inc: BEEP_FREQUENCY / spec.freq as f32,
+        phase: 0.0,
+        volume: BEEP_VOLUME,
+    })
+}
@@ -0,0 +1,170 @@
+use chip8_core::Quirks;
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// The config file read from the working directory, a game's own file and the command line override it.
+pub const CONFIG_FILE: &str = "chip8.toml";
+
+/// Config holds the settings read from a config file, every one of them is optional.
+///
+/// The file is TOML, with the same names and ranges as the command line options:
+///
+/// ```text
+/// scale = 10          # 1-50
+/// speed = 600         # instructions per second, 60-60000
+/// fg = "ffb000"       # RRGGBB
+/// bg = "000000"
+/// visual_beep = true
+/// beep_color = "ff0000"
+///
+/// [quirks]
+/// wrap_sprite_origin = true
+/// clip_sprite_body = true
+/// display_wait = false
+/// accumulate_draws = true
+/// ```
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub scale: Option<u32>,
+    pub speed: Option<u32>,
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub visual_beep: Option<bool>,
+    pub beep_color: Option<String>,
+    #[serde(default)]
+    pub quirks: QuirksConfig,
+}
+
+/// QuirksConfig holds the quirks set in a config file, the ones left out keep their default.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuirksConfig {
+    pub wrap_sprite_origin: Option<bool>,
+    pub clip_sprite_body: Option<bool>,
+    pub display_wait: Option<bool>,
+    pub accumulate_draws: Option<bool>,
+}
+
+impl Config {
+    /// Returns this config with every setting `over` sets replaced, e.g. the global config
+    /// merged with a game's own one.
+    pub fn merge(self, over: Config) -> Config
+    {
+        Config {
+            scale: over.scale.or(self.scale),
+            speed: over.speed.or(self.speed),
+            fg: over.fg.or(self.fg),
+            bg: over.bg.or(self.bg),
+            visual_beep: over.visual_beep.or(self.visual_beep),
+            beep_color: over.beep_color.or(self.beep_color),
+            quirks: QuirksConfig {
+                wrap_sprite_origin: over.quirks.wrap_sprite_origin.or(self.quirks.wrap_sprite_origin),
+                clip_sprite_body: over.quirks.clip_sprite_body.or(self.quirks.clip_sprite_body),
+                display_wait: over.quirks.display_wait.or(self.quirks.display_wait),
+                accumulate_draws: over.quirks.accumulate_draws.or(self.quirks.accumulate_draws),
+            },
+        }
+    }
+}
+
+impl QuirksConfig {
+    /// Returns `quirks` with the quirks set in the file replaced.
+    pub fn apply(&self, mut quirks: Quirks) -> Quirks
+    {
+        if let Some(wrap_sprite_origin) = self.wrap_sprite_origin {
+            quirks.wrap_sprite_origin = wrap_sprite_origin;
+        }
+        if let Some(clip_sprite_body) = self.clip_sprite_body {
+            quirks.clip_sprite_body = clip_sprite_body;
+        }
+        if let Some(display_wait) = self.display_wait {
+            quirks.display_wait = display_wait;
+        }
+        if let Some(accumulate_draws) = self.accumulate_draws {
+            quirks.accumulate_draws = accumulate_draws;
+        }
+        quirks
+    }
+}
+
+/// The config file of a game sits next to it, e.g. games/PONG.toml for games/PONG.ch8.
+pub fn game_config_path(rom_path: &str) -> PathBuf
+{
+    Path::new(rom_path).with_extension("toml")
+}
+
+/// Reads a config file, a missing file is the same as an empty one.
+///
+/// Returns a message describing the problem if the file can't be read or isn't valid.
+pub fn load_config(path: &Path) -> Result<Config, String>
+{
+    match fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|err| format!("Invalid config {}: {}", path.display(), err)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(format!("Unable to read {}: {}", path.display(), err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::{parse_args, resolve_options, DEFAULT_SCALE};
+    use chip8_core::DEFAULT_TARGET_IPS;
+
+    const FILE: &str = "scale = 4\nspeed = 900\nfg = \"ffb000\"\n\n[quirks]\ndisplay_wait = true\nclip_sprite_body = true\nwrap_sprite_origin = false\n";
+
+    #[test]
+    fn command_line_beats_the_config_file_which_beats_the_defaults() {
+        let file: Config = toml::from_str(FILE).unwrap();
+        let args = ["--speed", "1200", "game.ch8"].map(String::from);
+
+        let (rom_path, cli) = parse_args(&args).unwrap();
+
+        let options = resolve_options(rom_path, &file.merge(cli)).unwrap();
+        assert_eq!(options.scale, 4);
+        assert_eq!(options.target_ips, 1200);
+        assert_eq!(options.fg.rgb(), (0xFF, 0xB0, 0x00));
+        assert!(!options.visual_beep);
+        assert_eq!(options.quirks, Quirks { display_wait: true, wrap_sprite_origin: false, clip_sprite_body: true, ..Quirks::DEFAULT });
+
+        let options = resolve_options("game.ch8".to_string(), &Config::default()).unwrap();
+        assert_eq!(options.scale, DEFAULT_SCALE);
+        assert_eq!(options.target_ips, DEFAULT_TARGET_IPS);
+        assert_eq!(options.quirks, Quirks::default());
+    }
+
+    #[test]
+    fn bad_config_files_are_rejected() {
+        assert!(toml::from_str::<Config>("speed = \"fast\"").is_err());
+        assert!(toml::from_str::<Config>("colour = \"ffffff\"").is_err());
+
+        let file: Config = toml::from_str("scale = 100").unwrap();
+        assert!(resolve_options("game.ch8".to_string(), &file).is_err());
+    }
+
+    #[test]
+    fn game_config_overrides_the_global_one() {
+        let dir = std::env::temp_dir().join(format!("chip8_desktop_config_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rom_path = dir.join("PONG.ch8").to_string_lossy().into_owned();
+        let game_path = game_config_path(&rom_path);
+        fs::write(&game_path, "speed = 1500\n[quirks]\ndisplay_wait = false\n").unwrap();
+
+        let game = load_config(&game_path);
+        let missing = load_config(&dir.join("TETRIS.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(game_path, dir.join("PONG.toml"));
+        assert!(missing.unwrap().speed.is_none());
+
+        let global: Config = toml::from_str(FILE).unwrap();
+        let config = global.merge(game.unwrap()).merge(Config::default());
+        assert_eq!(config.scale, Some(4));
+        assert_eq!(config.speed, Some(1500));
+        assert_eq!(config.quirks.display_wait, Some(false));
+        assert_eq!(config.quirks.clip_sprite_body, Some(true));
+    }
+}
@@ -0,0 +1,58 @@
+use sdl2::controller::{Button, GameController};
+use sdl2::GameControllerSubsystem;
+
+/// Default mapping from gamepad buttons to CHIP-8 keys, edit it to suit a game.
+///
+/// Most games move with 2/4/6/8 and act with 5, so the D-pad sits on those keys:
+///
+/// | Button      | Key | Button | Key |
+/// |-------------|-----|--------|-----|
+/// | D-pad up    | 2   | A      | 5   |
+/// | D-pad down  | 8   | B      | 6   |
+/// | D-pad left  | 4   | X      | 7   |
+/// | D-pad right | 6   | Y      | 9   |
+/// | Back        | 1   | Start  | F   |
+/// | LB          | A   | RB     | B   |
+pub const CONTROLLER_MAPPING: &[(Button, usize)] = &[
+    (Button::DPadUp, 0x2),
+    (Button::DPadDown, 0x8),
+    (Button::DPadLeft, 0x4),
+    (Button::DPadRight, 0x6),
+    (Button::A, 0x5),
+    (Button::B, 0x6),
+    (Button::X, 0x7),
+    (Button::Y, 0x9),
+    (Button::Back, 0x1),
+    (Button::Start, 0xF),
+    (Button::LeftShoulder, 0xA),
+    (Button::RightShoulder, 0xB),
+];
+
+pub fn button2btn(button: Button) -> Option<usize>
+{
+    CONTROLLER_MAPPING
+        .iter()
+        .find(|(mapped, _)| *mapped == button)
+        .map(|&(_, btn)| btn)
+}
+
+/// Opens the controller at `joystick_index` unless it's already in `controllers`.
+///
+/// SDL reports controllers plugged in before start as added too, so the same
+/// device can show up twice. Joysticks that aren't game controllers are ignored.
+pub fn open_controller(subsystem: &GameControllerSubsystem, controllers: &mut Vec<GameController>, joystick_index: u32)
+{
+    if !subsystem.is_game_controller(joystick_index) {
+        return;
+    }
+
+    match subsystem.open(joystick_index) {
+        Ok(controller) => {
+            if controllers.iter().all(|open| open.instance_id() != controller.instance_id()) {
+                println!("Using controller {}", controller.name());
+                controllers.push(controller);
+            }
+        },
+        Err(err) => eprintln!("Unable to open controller {}: {}", joystick_index, err),
+    }
+}
@@ -0,0 +1,81 @@
+use chip8_core::Emu;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::rwops::RWops;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::Window;
+
+// DejaVu Sans Mono, see assets/LICENSE-DejaVu.txt
+const FONT: &[u8] = include_bytes!("../assets/DejaVuSansMono.ttf");
+const FONT_SIZE: u16 = 14;
+
+// Space between the text and the edge of the background box
+const PADDING: i32 = 6;
+
+/// Hud draws the CPU state in the top left corner, over a semi-transparent box.
+pub struct Hud<'ttf> {
+    font: Font<'ttf, 'static>,
+}
+
+impl<'ttf> Hud<'ttf> {
+    pub fn new(ttf_context: &'ttf Sdl2TtfContext) -> Result<Self, String>
+    {
+        let font = ttf_context.load_font_from_rwops(RWops::from_bytes(FONT)?, FONT_SIZE)?;
+
+        Ok(Hud { font })
+    }
+
+    pub fn draw(&self, emu: &Emu, canvas: &mut Canvas<Window>) -> Result<(), String>
+    {
+        let mut lines = vec![
+            format!("PC {:04X}  I {:04X}", emu.pc(), emu.i_reg()),
+            format!("SP {:2}  DT {:3}  ST {:3}", emu.sp(), emu.delay_timer(), emu.sound_timer()),
+        ];
+
+        // Four registers per line
+        for (row, regs) in emu.registers().chunks(4).enumerate() {
+            let line = regs
+                .iter()
+                .enumerate()
+                .map(|(col, value)| format!("V{:X} {:02X}", row * 4 + col, value))
+                .collect::<Vec<_>>()
+                .join("  ");
+            lines.push(line);
+        }
+
+        let keys: String = emu.pressed_keys().map(|key| format!(" {:X}", key)).collect();
+        lines.push(format!("Keys{}", keys));
+
+        let line_height = self.font.recommended_line_spacing();
+        let mut width = 0;
+        for line in &lines {
+            let (line_width, _) = self.font.size_of(line).map_err(|err| err.to_string())?;
+            width = width.max(line_width);
+        }
+        let height = line_height * lines.len() as i32;
+
+        // The game stays visible through the background
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 170));
+        canvas.fill_rect(Rect::new(0, 0, width + 2 * PADDING as u32, (height + 2 * PADDING) as u32))?;
+        canvas.set_blend_mode(BlendMode::None);
+
+        let texture_creator = canvas.texture_creator();
+        for (idx, line) in lines.iter().enumerate() {
+            let surface = self
+                .font
+                .render(line)
+                .blended(Color::RGB(255, 255, 0))
+                .map_err(|err| err.to_string())?;
+            let texture = texture_creator
+                .create_texture_from_surface(&surface)
+                .map_err(|err| err.to_string())?;
+
+            let target = Rect::new(PADDING, PADDING + idx as i32 * line_height, surface.width(), surface.height());
+            canvas.copy(&texture, None, Some(target))?;
+        }
+
+        Ok(())
+    }
+}
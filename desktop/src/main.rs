@@ -1,7 +1,25 @@
+mod args;
+mod audio;
+mod config;
+mod controller;
+#[cfg(feature = "hud")]
+mod hud;
+mod screenshot;
+mod stats;
+
+use args::{parse_args, resolve_options, Options, MAX_TARGET_IPS, MIN_TARGET_IPS, USAGE};
+use audio::open_beep;
+use config::{game_config_path, load_config, Config, CONFIG_FILE};
+use controller::{button2btn, open_controller};
+#[cfg(feature = "hud")]
+use hud::Hud;
+use screenshot::save_screenshot;
+use stats::Stats;
 use chip8_core::*;
+use chip8_core::disasm::disassemble;
 use std::env;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
+use std::path::{Path, PathBuf};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
@@ -9,34 +27,83 @@ use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
 
-const SCALE: u32 = 15;
-const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
-const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
-const TICKS_PER_FRAME: usize = 10;
+// How much faster the CPU runs while Tab is held
+const TURBO_MULTIPLIER: usize = 10;
 
-fn draw_screen(emu: &Emu, canvas: &mut Canvas<Window>)
+// Draw the screen, with a border in the beep color while the visual beep is showing
+fn draw_screen(emu: &Emu, canvas: &mut Canvas<Window>, scale: u32, fg: Color, bg: Color, beep: Option<Color>)
 {
-    // Clear canvas as black
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    // Clear canvas with the background color
+    canvas.set_draw_color(bg);
     canvas.clear();
 
-    let screen_buf = emu.get_display();
+    // Now set draw color to the foreground color, and draw every lit point
+    canvas.set_draw_color(fg);
 
-    // Now set draw color to white, iterate through each point and see if it should be drawn
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    // The window is sized for 64x32, hires pixels are half as large. Every pixel ends where
+    // the next one starts, so an odd scale doesn't leave gaps at the right and bottom
+    let (width, height) = emu.screen_dimensions();
+    let (width, height) = (width as u32, height as u32);
+    let window_width = SCREEN_WIDTH as u32 * scale;
+    let window_height = SCREEN_HEIGHT as u32 * scale;
 
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            // Convert our 1D array's index into a 2D (x,y) position 
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
+    for (x, y) in emu.lit_pixels() {
+        let (x, y) = (x as u32, y as u32);
 
-            // Draw a rectangle at (x,y), scaled up by our SCALE value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
-            canvas.fill_rect(rect).unwrap();
-        }
+        // Draw a rectangle from (x,y) to the next pixel, scaled up to the window
+        let (x0, x1) = (x * window_width / width, (x + 1) * window_width / width);
+        let (y0, y1) = (y * window_height / height, (y + 1) * window_height / height);
+        let rect = Rect::new(x0 as i32, y0 as i32, x1 - x0, y1 - y0);
+        canvas.fill_rect(rect).unwrap();
+    }
+
+    if let Some(beep_color) = beep {
+        let (width, height) = canvas.output_size().unwrap();
+        let thickness = (scale / 2).max(2);
+
+        canvas.set_draw_color(beep_color);
+        canvas.fill_rects(&[
+            Rect::new(0, 0, width, thickness),
+            Rect::new(0, (height - thickness) as i32, width, thickness),
+            Rect::new(0, 0, thickness, height),
+            Rect::new((width - thickness) as i32, 0, thickness, height),
+        ]).unwrap();
     }
-    canvas.present();
+}
+
+// Show the ROM, the emulation state and the speed, targeted and measured, in the window title
+fn update_title(canvas: &mut Canvas<Window>, rom_name: &str, target_ips: u32, paused: bool, stats: &Stats)
+{
+    let state = if paused { " [paused]" } else { "" };
+    let title = format!(
+        "Chip-8 Emulator - {} - {} IPS target - {:.0} FPS, {:.0} IPS{}",
+        rom_name, target_ips, stats.fps, stats.ips, state
+    );
+    canvas.window_mut().set_title(&title).unwrap();
+}
+
+// The ROM's file name, shown in the window title
+fn rom_name(rom_path: &str) -> String
+{
+    Path::new(rom_path)
+        .file_name()
+        .map_or(rom_path.to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+// ROMs ending in .gz are decompressed first
+fn load_rom(emu: &mut Emu, rom_path: &str, data: &[u8]) -> Result<(), Chip8Error>
+{
+    if Path::new(rom_path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz")) {
+        emu.load_gz(data)
+    } else {
+        emu.try_load(data)
+    }
+}
+
+// The quicksave lives next to the ROM, e.g. games/PONG.ch8 is saved to games/PONG.sav
+fn save_path(rom_path: &str) -> PathBuf
+{
+    Path::new(rom_path).with_extension("sav")
 }
 
 fn key2btn(key: Keycode) -> Option<usize> {
@@ -61,15 +128,47 @@ fn key2btn(key: Keycode) -> Option<usize> {
     }
 }
 
+// resolve the settings for a game, its own config file overrides the global one and the command line overrides both
+fn game_options(rom_path: String, global_config: &Config, cli_config: &Config) -> Result<Options, String>
+{
+    let game_config = load_config(&game_config_path(&rom_path))?;
+    let config = global_config.clone().merge(game_config).merge(cli_config.clone());
+
+    resolve_options(rom_path, &config)
+}
+
 fn main() {
     // _ means that type of Vector is not sure
     // it depends on the type of the arguments
     let args: Vec<_> = env::args().collect();
 
-    if args.len() != 2 {
-        println!("Usage: cargo run path/to/game");
-        return;
-    }
+    let global_config = match load_config(Path::new(CONFIG_FILE)) {
+        Ok(config) => config,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let (rom_path, cli_config) = match parse_args(&args[1..]) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            println!("{}\n\n{}", err, USAGE);
+            return;
+        }
+    };
+
+    let mut options = match game_options(rom_path, &global_config, &cli_config) {
+        Ok(options) => options,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    // The window size follows the chosen scale
+    let window_width = (SCREEN_WIDTH as u32) * options.scale;
+    let window_height = (SCREEN_HEIGHT as u32) * options.scale;
 
     // Start the SDL2 context. This is a handle to the library's functionality.
     let sdl_context = sdl2::init().unwrap();
@@ -79,10 +178,10 @@ fn main() {
     let video_subsystem = sdl_context.video().unwrap();
 
     // Create a window with the title "Chip-8 Emulator". 
-    // The window will have a width as WINDOW_WIDTH and height as WINDOW_HEIGHT. 
+    // The window will have a width as window_width and height as window_height. 
     // Set the position of the window to centered, and use opengl as the window's backend.
     let window = video_subsystem
-        .window("Chip-8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .window("Chip-8 Emulator", window_width, window_height)
         .position_centered()
         .opengl()
         .build()
@@ -103,17 +202,57 @@ fn main() {
     canvas.present();
 
 
+    // The beep plays while the sound timer is running
+    let audio_subsystem = sdl_context.audio().unwrap();
+    let beep = open_beep(&audio_subsystem).unwrap();
+
+    // Toggled with L
+    let mut muted = false;
+
+    // Controllers are kept open for as long as they are plugged in,
+    // ones connected later are picked up by the ControllerDeviceAdded event
+    let controller_subsystem = sdl_context.game_controller().unwrap();
+    let mut controllers = Vec::new();
+    let num_joysticks = controller_subsystem.num_joysticks().unwrap_or(0);
+    if let Some(first) = (0..num_joysticks).find(|&idx| controller_subsystem.is_game_controller(idx)) {
+        open_controller(&controller_subsystem, &mut controllers, first);
+    }
+
+    // Built with the hud feature, H toggles the register overlay
+    #[cfg(feature = "hud")]
+    let ttf_context = sdl2::ttf::init().unwrap();
+    #[cfg(feature = "hud")]
+    let hud = Hud::new(&ttf_context).unwrap();
+    #[cfg(feature = "hud")]
+    let mut show_hud = false;
+
     // SDL provides this method to poll for events every loop.
     // Should use poll_iter to get all available events
     let mut event_pump = sdl_context.event_pump().unwrap();
 
+    // read data from file and load into Emu
+    // the buffer is kept around so the game can be restarted without reading the file again
+    // a ROM dropped on the window replaces it, along with its path, name and quicksave path
+    let mut rom_path = options.rom_path.clone();
+    let mut buffer = fs::read(&rom_path).expect("Unable to open file");
     let mut chip8 = Emu::new();
+    chip8.set_quirks(options.quirks);
+    load_rom(&mut chip8, &rom_path, &buffer).expect("Unable to load ROM");
+    // Adjusted at runtime with the [ and ] keys, reset keeps it
+    chip8.set_target_ips(options.target_ips);
 
-    // read data from file and load into Emu
-    let mut rom = File::open(&args[1]).expect("Unable to open file");
-    let mut buffer = Vec::new();
-    rom.read_to_end(&mut buffer).unwrap();
-    chip8.load(&buffer);
+    let mut save_path = save_path(&rom_path);
+
+    // Toggled with Space, and set when the ROM hits an error, the window stays open either way
+    let mut paused = false;
+
+    // The title is refreshed whenever the state changes, and once per second with the measured speed
+    let mut rom_name = rom_name(&rom_path);
+    let mut stats = Stats::new(&chip8);
+    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+
+    // True while Tab is held
+    let mut turbo = false;
 
     // ‘gameloop is a loop label， it can let us easy to break the specific loop
     'gameloop: loop {
@@ -126,6 +265,116 @@ fn main() {
                     // In the here, we can break specific loop by loop label 'gameloop
                     break 'gameloop;
                 },
+                // Slow down or speed up, in steps of roughly 10%
+                Event::KeyDown {keycode: Some(Keycode::LeftBracket), ..} => {
+                    let step = (chip8.target_ips() / 10).max(1);
+                    chip8.set_target_ips(chip8.target_ips().saturating_sub(step).max(MIN_TARGET_IPS));
+                    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                },
+                Event::KeyDown {keycode: Some(Keycode::RightBracket), ..} => {
+                    let step = (chip8.target_ips() / 10).max(1);
+                    chip8.set_target_ips((chip8.target_ips() + step).min(MAX_TARGET_IPS));
+                    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                },
+                // Restart the game from 0x200 with a clean machine, Escape stays the quit key
+                Event::KeyDown {keycode: Some(Keycode::Backspace), ..} => {
+                    chip8.reset();
+                    // the ROM already loaded once, so it can't fail now
+                    load_rom(&mut chip8, &rom_path, &buffer).unwrap();
+                    paused = false;
+                    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                },
+                // Switch to a ROM dropped on the window, the current game keeps running if it can't be loaded
+                Event::DropFile {filename, ..} => {
+                    match fs::read(&filename) {
+                        Ok(data) => {
+                            // Loaded into a reset copy so the settings carry over and a bad ROM changes nothing
+                            let mut next = chip8.clone();
+                            next.reset();
+                            let next_options = match game_options(filename.clone(), &global_config, &cli_config) {
+                                Ok(next_options) => next_options,
+                                Err(err) => {
+                                    eprintln!("Unable to load {}: {}", filename, err);
+                                    continue;
+                                },
+                            };
+                            next.set_quirks(next_options.quirks);
+                            match load_rom(&mut next, &filename, &data) {
+                                Ok(()) => {
+                                    chip8 = next;
+                                    chip8.set_target_ips(next_options.target_ips);
+                                    // The window keeps its size, so only the scale of the first game applies
+                                    options = Options { scale: options.scale, ..next_options };
+                                    buffer = data;
+                                    save_path = self::save_path(&filename);
+                                    rom_name = self::rom_name(&filename);
+                                    rom_path = filename;
+                                    paused = false;
+                                },
+                                Err(err) => eprintln!("Unable to load {}: {}", filename, err),
+                            }
+                            update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                        },
+                        Err(err) => eprintln!("Unable to open {}: {}", filename, err),
+                    }
+                },
+                // Quicksave, the previous save is overwritten
+                Event::KeyDown {keycode: Some(Keycode::F5), repeat: false, ..} => {
+                    match fs::write(&save_path, chip8.save_state()) {
+                        Ok(()) => println!("Saved state to {}", save_path.display()),
+                        Err(err) => eprintln!("Unable to save state to {}: {}", save_path.display(), err),
+                    }
+                },
+                // Quickload, load_state leaves the running game untouched if the file is bad
+                Event::KeyDown {keycode: Some(Keycode::F9), repeat: false, ..} => {
+                    match fs::read(&save_path) {
+                        Ok(data) => match chip8.load_state(&data) {
+                            Ok(()) => println!("Loaded state from {}", save_path.display()),
+                            Err(err) => eprintln!("Unable to load state from {}: {}", save_path.display(), err),
+                        },
+                        Err(err) => eprintln!("Unable to read {}: {}", save_path.display(), err),
+                    }
+                },
+                // While paused, N executes a single instruction and M ticks the timers once
+                Event::KeyDown {keycode: Some(Keycode::N), ..} if paused => {
+                    let pc = chip8.pc();
+                    let op = (chip8.peek_ram(pc) as u16) << 8 | chip8.peek_ram(pc.wrapping_add(1)) as u16;
+                    println!("0x{:03X}: {:04X}  {}", pc, op, disassemble(op));
+
+                    if let Err(err) = chip8.try_tick() {
+                        eprintln!("Step failed: {}", err);
+                    }
+                },
+                Event::KeyDown {keycode: Some(Keycode::M), ..} if paused => {
+                    chip8.tick_timers();
+                    println!("DT = {}, ST = {}", chip8.delay_timer(), chip8.sound_timer());
+                },
+                #[cfg(feature = "hud")]
+                Event::KeyDown {keycode: Some(Keycode::H), repeat: false, ..} => {
+                    show_hud = !show_hud;
+                },
+                // Turbo only speeds up the CPU, the timers still tick once per frame
+                Event::KeyDown {keycode: Some(Keycode::Tab), repeat: false, ..} => {
+                    turbo = true;
+                },
+                Event::KeyUp {keycode: Some(Keycode::Tab), ..} => {
+                    turbo = false;
+                },
+                // Pause or resume, events are still handled and the screen is still drawn while paused
+                Event::KeyDown {keycode: Some(Keycode::Space), repeat: false, ..} => {
+                    paused = !paused;
+                    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                },
+                Event::KeyDown {keycode: Some(Keycode::L), repeat: false, ..} => {
+                    muted = !muted;
+                },
+                // Capture the screen, a failed write is only logged
+                Event::KeyDown {keycode: Some(Keycode::F12), repeat: false, ..} => {
+                    match save_screenshot(&chip8, options.scale, options.fg.rgb().into(), options.bg.rgb().into()) {
+                        Ok(file_name) => println!("Saved screenshot to {}", file_name),
+                        Err(err) => eprintln!("Unable to save screenshot: {}", err),
+                    }
+                },
                 Event::KeyDown {keycode: Some(key), .. } => {
                     if let Some(k) = key2btn(key) {
                         chip8.keypress(k,true);
@@ -136,15 +385,80 @@ fn main() {
                         chip8.keypress(k,false);
                     }
                 }
+                Event::ControllerButtonDown {button, ..} => {
+                    if let Some(k) = button2btn(button) {
+                        chip8.keypress(k, true);
+                    }
+                }
+                Event::ControllerButtonUp {button, ..} => {
+                    if let Some(k) = button2btn(button) {
+                        chip8.keypress(k, false);
+                    }
+                }
+                Event::ControllerDeviceAdded {which, ..} => {
+                    open_controller(&controller_subsystem, &mut controllers, which);
+                }
+                Event::ControllerDeviceRemoved {which, ..} => {
+                    controllers.retain(|controller| controller.instance_id() != which);
+                }
                 // For all other types of events, we don't do anything and just continue looping.
                 _ => ()
             }
         }
 
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.tick();
+        if !paused {
+            let ticks_per_frame = chip8.ticks_per_frame();
+            let ticks = if turbo { ticks_per_frame * TURBO_MULTIPLIER } else { ticks_per_frame };
+            for _ in 0..ticks {
+                if let Err(err) = chip8.try_tick() {
+                    // The CRC identifies the ROM in bug reports
+                    match chip8.loaded_rom_hash() {
+                        Some(hash) => eprintln!("Emulation paused: {} (ROM CRC32 {:08X})", err, hash),
+                        None => eprintln!("Emulation paused: {}", err),
+                    }
+                    paused = true;
+                    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                    break;
+                }
+
+                // With the display_wait quirk a DRAW ends the frame, further ticks would do nothing
+                if chip8.waiting_for_vblank() {
+                    break;
+                }
+
+                // The last screen stays up, Backspace restarts the game
+                if chip8.halted() {
+                    eprintln!("Program ended");
+                    paused = true;
+                    update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
+                    break;
+                }
+            }
+            chip8.tick_timers();
+        }
+
+        // Pause the device rather than closing it, so the next beep starts right away
+        if chip8.is_beeping() && !paused && !muted {
+            beep.resume();
+        } else {
+            beep.pause();
+        }
+
+        // The visual beep ignores mute, it's meant for when the sound can't be heard
+        let beep_border = (options.visual_beep && chip8.is_beeping() && !paused).then_some(options.beep_color);
+        draw_screen(&chip8, &mut canvas, options.scale, options.fg, options.bg, beep_border);
+
+        #[cfg(feature = "hud")]
+        if show_hud {
+            if let Err(err) = hud.draw(&chip8, &mut canvas) {
+                eprintln!("Unable to draw the HUD: {}", err);
+            }
+        }
+
+        canvas.present();
+
+        if stats.frame(&chip8) {
+            update_title(&mut canvas, &rom_name, chip8.target_ips(), paused, &stats);
         }
-        chip8.tick_timers();
-        draw_screen(&chip8, &mut canvas);
     }
 }
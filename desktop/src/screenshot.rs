@@ -0,0 +1,28 @@
+use chip8_core::Emu;
+use image::{ImageResult, RgbaImage};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes the current display to a PNG file in the working directory and returns its name.
+///
+/// Every CHIP-8 pixel becomes a `scale` x `scale` block, set pixels use `fg` and unset pixels `bg`.
+/// The file name carries a timestamp so repeated captures don't overwrite each other.
+pub fn save_screenshot(emu: &Emu, scale: u32, fg: [u8; 3], bg: [u8; 3]) -> ImageResult<String>
+{
+    let (width, height) = emu.screen_dimensions();
+
+    let [fg_r, fg_g, fg_b] = fg;
+    let [bg_r, bg_g, bg_b] = bg;
+    let pixels = emu.render_rgba([fg_r, fg_g, fg_b, 0xFF], [bg_r, bg_g, bg_b, 0xFF], scale as usize);
+
+    // render_rgba sizes the buffer to match, so this can't fail
+    let image = RgbaImage::from_raw(width as u32 * scale, height as u32 * scale, pixels).unwrap();
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let file_name = format!("chip8-{}.png", timestamp);
+
+    image.save(&file_name)?;
+
+    Ok(file_name)
+}
@@ -0,0 +1,50 @@
+use chip8_core::Emu;
+use std::time::{Duration, Instant};
+
+// How often the measured rates are refreshed
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Stats measures the frames drawn and the instructions executed per second of wall-clock time.
+pub struct Stats {
+    last_sample: Instant,
+    frames: u64,
+    last_cycles: u64,
+    pub fps: f64,
+    pub ips: f64,
+}
+
+impl Stats {
+    pub fn new(emu: &Emu) -> Self
+    {
+        Stats {
+            last_sample: Instant::now(),
+            frames: 0,
+            last_cycles: emu.cycle_count(),
+            fps: 0.0,
+            ips: 0.0,
+        }
+    }
+
+    /// Counts a drawn frame, returns true about once per second when the rates were refreshed.
+    pub fn frame(&mut self, emu: &Emu) -> bool
+    {
+        self.frames += 1;
+
+        let elapsed = self.last_sample.elapsed();
+        if elapsed < SAMPLE_PERIOD {
+            return false;
+        }
+
+        // A reset or a loaded state can move the cycle counter backwards
+        let cycles = emu.cycle_count().saturating_sub(self.last_cycles);
+
+        self.fps = self.frames as f64 / elapsed.as_secs_f64();
+        self.ips = cycles as f64 / elapsed.as_secs_f64();
+
+        self.last_sample = Instant::now();
+        self.frames = 0;
+        self.last_cycles = emu.cycle_count();
+
+        true
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use js_sys::Uint8Array;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen::prelude::wasm_bindgen;
@@ -7,7 +8,17 @@ use chip8_core::{Emu, SCREEN_HEIGHT, SCREEN_WIDTH};
 #[wasm_bindgen]
 pub struct EmuWasm {
     chip8: Emu,
-    ctx: CanvasRenderingContext2d
+    // None without a canvas on the page, e.g. in the tests under node, draw_screen then does nothing
+    ctx: Option<CanvasRenderingContext2d>,
+    // keys remapped by set_key_mapping, checked before the default layout
+    key_mapping: HashMap<String, usize>,
+    // CSS colors of lit and unlit pixels, changed with set_colors
+    fg: String,
+    bg: String,
+    // set by pause, the JS main loop skips its batched ticks while it's set
+    paused: bool,
+    // time carried over between tick_timers_for calls
+    timer_clock: TimerClock,
 }
 
 #[wasm_bindgen]
@@ -34,12 +45,60 @@ impl EmuWasm {
             .dyn_into::<CanvasRenderingContext2d>()
             .unwrap();
         
-        Ok(EmuWasm{chip8,ctx})
+        Ok(EmuWasm::with_context(chip8, Some(ctx)))
     }
 
+    // throws if the ROM hits an error, so JS can stop the main loop
     #[wasm_bindgen]
-    pub fn tick(&mut self) {
-        self.chip8.tick();
+    pub fn tick(&mut self) -> Result<(), JsValue> {
+        self.chip8
+            .try_tick()
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    // runs up to n instructions in a single call, so a frame doesn't cross into wasm n times
+    // throws at the first error, like tick
+    #[wasm_bindgen]
+    pub fn tick_many(&mut self, n: usize) -> Result<(), JsValue> {
+        for _ in 0..n {
+            self.tick()?;
+        }
+
+        Ok(())
+    }
+
+    // runs a whole frame in a single call: ticks_per_frame instructions, fewer if a DRAW
+    // waits for the vertical blank, then tick_timers
+    // returns whether the screen changed, so JS can skip the redraw, and throws on error:
+    //     if (chip8.run_frame(ticksPerFrame)) { chip8.draw_screen(scale); }
+    #[wasm_bindgen]
+    pub fn run_frame(&mut self, ticks_per_frame: usize) -> Result<bool, JsValue> {
+        self.chip8
+            .run_frame(ticks_per_frame)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+        Ok(self.chip8.take_screen_dirty())
+    }
+
+    // runs exactly one instruction, paused or not, for single stepping in a debugger
+    #[wasm_bindgen]
+    pub fn step(&mut self) -> Result<(), JsValue> {
+        self.tick()
+    }
+
+    #[wasm_bindgen]
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    #[wasm_bindgen]
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    #[wasm_bindgen]
+    pub fn is_paused(&self) -> bool {
+        self.paused
     }
 
     #[wasm_bindgen]
@@ -47,43 +106,210 @@ impl EmuWasm {
         self.chip8.tick_timers();
     }
 
+    // ticks the timers at 60Hz whatever the display's refresh rate, with the time since the
+    // previous call, e.g. the difference between two requestAnimationFrame timestamps
+    // returns how many times the timers ticked, so JS can run that many frames of instructions
+    #[wasm_bindgen]
+    pub fn tick_timers_for(&mut self, delta_ms: f64) -> u32 {
+        let frames = self.timer_clock.advance(delta_ms);
+        for _ in 0..frames {
+            self.chip8.tick_timers();
+        }
+
+        frames
+    }
+
+    // true when the screen changed since the last call, for loops that don't use run_frame
+    #[wasm_bindgen]
+    pub fn take_screen_dirty(&mut self) -> bool {
+        self.chip8.take_screen_dirty()
+    }
+
+    // true while the sound timer is running, JS should play the beep while this is set
+    // see index.js: one oscillator is started once and its gain is switched on and off every frame
+    #[wasm_bindgen]
+    pub fn is_beeping(&self) -> bool {
+        self.chip8.is_beeping()
+    }
+
+    // frames left on the sound timer, for frontends that want to fade the beep out
+    #[wasm_bindgen]
+    pub fn get_sound_timer(&self) -> u8 {
+        self.chip8.sound_timer()
+    }
+
     #[wasm_bindgen]
     pub fn reset(&mut self) {
         self.chip8.reset();
+        self.timer_clock = TimerClock::default();
     }
 
     #[wasm_bindgen]
     pub fn keypress(&mut self, evt: KeyboardEvent, pressed: bool) {
-        let key = evt.key();
-        if let Some(k) = key2btn(&key) {
-            self.chip8.keypress(k, pressed);
+        self.key_changed(&evt.key(), pressed);
+    }
+
+    // bind a KeyboardEvent.key value to a CHIP-8 button (0x0 to 0xF)
+    // throws for an invalid button, so a settings UI can report it
+    #[wasm_bindgen]
+    pub fn set_key_mapping(&mut self, key: &str, btn: u8) -> Result<(), JsValue> {
+        if btn > 0xF {
+            return Err(JsValue::from_str(&format!("Invalid CHIP-8 button: {}", btn)));
         }
+
+        self.key_mapping.insert(key.to_string(), btn as usize);
+        Ok(())
     }
     
+    // opaque, versioned snapshot of the whole machine, suitable for localStorage
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> Uint8Array {
+        Uint8Array::from(self.chip8.save_state().as_slice())
+    }
+
+    // restore a snapshot from save_state, throws and leaves the game untouched if it's invalid
     #[wasm_bindgen]
-    pub fn load_game(&mut self, data: Uint8Array) {
-        self.chip8.load(&data.to_vec())
+    pub fn load_state(&mut self, data: Uint8Array) -> Result<(), JsValue> {
+        self.chip8
+            .load_state(&data.to_vec())
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    // throws and leaves memory untouched if the ROM doesn't fit, e.g. a file that isn't a ROM
+    #[wasm_bindgen]
+    pub fn load_game(&mut self, data: Uint8Array) -> Result<(), JsValue> {
+        self.chip8
+            .try_load(&data.to_vec())
+            .map_err(|err| JsValue::from_str(&err.to_string()))
     }
     
+    // one byte per pixel, 1 when lit and 0 otherwise, row by row from the top left corner
+    #[wasm_bindgen]
+    pub fn get_display(&self) -> Vec<u8> {
+        self.chip8
+            .get_display()
+            .iter()
+            .map(|&pixel| pixel as u8)
+            .collect()
+    }
+
+    // 8 pixels per byte, row by row from the top left corner
+    // the most significant bit of each byte is the leftmost pixel, so pixel i is
+    // (packed[i >> 3] >> (7 - (i & 7))) & 1, and the length is width * height / 8
+    #[wasm_bindgen]
+    pub fn get_display_packed(&self) -> Vec<u8> {
+        self.chip8
+            .get_display_bits()
+            .iter()
+            .flat_map(|word| word.to_be_bytes())
+            .collect()
+    }
+
+    // any CSS color works, e.g. "#ffb000" or "amber", an empty or malformed color is ignored
+    // and the previous one is kept
+    #[wasm_bindgen]
+    pub fn set_colors(&mut self, fg: &str, bg: &str) {
+        if is_css_color(fg) {
+            self.fg = fg.trim().to_string();
+        }
+        if is_css_color(bg) {
+            self.bg = bg.trim().to_string();
+        }
+    }
+
+    // clears the whole canvas to the background color, then draws the lit pixels
     #[wasm_bindgen]
     pub fn draw_screen(&mut self, scale: usize)
     {
-        let disp = self.chip8.get_display();
-        for i in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-            if disp[i] { 
-                let x = i % SCREEN_WIDTH;
-                let y = i / SCREEN_WIDTH;
-                self.ctx.fill_rect(
-                    (x * scale) as f64,
-                    (y * scale) as f64,
-                    scale as f64,
-                    scale as f64
-                );
-            }
+        let Some(ctx) = &self.ctx else { return };
+
+        if let Some(canvas) = ctx.canvas() {
+            ctx.set_fill_style_str(&self.bg);
+            ctx.fill_rect(0.0, 0.0, canvas.width() as f64, canvas.height() as f64);
+        }
+
+        // The canvas is sized for 64x32, hires pixels are half as large. Every pixel ends
+        // where the next one starts, so an odd scale doesn't leave gaps
+        let (width, height) = self.chip8.screen_dimensions();
+        let canvas_width = SCREEN_WIDTH * scale;
+        let canvas_height = SCREEN_HEIGHT * scale;
+
+        ctx.set_fill_style_str(&self.fg);
+        for (x, y) in self.chip8.lit_pixels() {
+            let (x0, x1) = (x * canvas_width / width, (x + 1) * canvas_width / width);
+            let (y0, y1) = (y * canvas_height / height, (y + 1) * canvas_height / height);
+            ctx.fill_rect(
+                x0 as f64,
+                y0 as f64,
+                (x1 - x0) as f64,
+                (y1 - y0) as f64
+            );
         }
     }
 }
 
+impl EmuWasm {
+    fn with_context(chip8: Emu, ctx: Option<CanvasRenderingContext2d>) -> EmuWasm {
+        EmuWasm {
+            chip8,
+            ctx,
+            key_mapping: HashMap::new(),
+            fg: DEFAULT_FG.to_string(),
+            bg: DEFAULT_BG.to_string(),
+            paused: false,
+            timer_clock: TimerClock::default(),
+        }
+    }
+
+    // press or release the button bound to a KeyboardEvent.key value, if there is one
+    fn key_changed(&mut self, key: &str, pressed: bool) {
+        let btn = self.key_mapping.get(key).copied().or_else(|| key2btn(key));
+        if let Some(k) = btn {
+            self.chip8.keypress(k, pressed);
+        }
+    }
+}
+
+// the timers run at 60Hz
+const TIMER_PERIOD_MS: f64 = 1000.0 / 60.0;
+
+// a tab that comes back from the background sees one huge delta, any time past this
+// many frames is dropped instead of fast forwarding the game
+const MAX_TIMER_FRAMES: u32 = 6;
+
+// turns the elapsed time into whole 60Hz frames, the fraction left is kept for the next call
+#[derive(Default)]
+struct TimerClock {
+    elapsed_ms: f64,
+}
+
+impl TimerClock {
+    fn advance(&mut self, delta_ms: f64) -> u32 {
+        // NaN and negative deltas don't move the clock
+        if delta_ms > 0.0 {
+            self.elapsed_ms += delta_ms;
+        }
+
+        let frames = (self.elapsed_ms / TIMER_PERIOD_MS) as u32;
+        if frames > MAX_TIMER_FRAMES {
+            self.elapsed_ms = 0.0;
+            return MAX_TIMER_FRAMES;
+        }
+
+        self.elapsed_ms -= frames as f64 * TIMER_PERIOD_MS;
+        frames
+    }
+}
+
+const DEFAULT_FG: &str = "white";
+const DEFAULT_BG: &str = "black";
+
+// a minimal sanity check, the canvas itself ignores colors it can't parse
+fn is_css_color(color: &str) -> bool {
+    let color = color.trim();
+    !color.is_empty() && !color.contains([';', '{', '}'])
+}
+
 fn key2btn(key: &str) -> Option<usize> {
     match key {
         "1" => Some(0x1),
@@ -104,4 +330,191 @@ fn key2btn(key: &str) -> Option<usize> {
         "v" => Some(0xF),
         _ => None,
     }
-}
\ No newline at end of file
+}
+
+// the bindings create JS values, so their tests only run on wasm32, under node:
+//     CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUNNER=wasm-bindgen-test-runner cargo test --target wasm32-unknown-unknown
+// the clock doesn't touch the DOM, so its tests also run natively with cargo test
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an emulator with no canvas, node has no DOM
+    #[cfg(target_arch = "wasm32")]
+    fn headless() -> EmuWasm {
+        EmuWasm::with_context(Emu::with_seed(0), None)
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn timer_clock_ticks_at_60hz_on_a_120hz_display() {
+        let mut clock = TimerClock::default();
+        let frames: u32 = (0..120).map(|_| clock.advance(1000.0 / 120.0)).sum();
+
+        assert_eq!(frames, 60);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn timer_clock_carries_the_fraction_over() {
+        let mut clock = TimerClock::default();
+
+        assert_eq!(clock.advance(12.0), 0);
+        assert_eq!(clock.advance(12.0), 1);
+        assert_eq!(clock.advance(30.0), 2);
+        assert_eq!(clock.advance(-5.0), 0);
+    }
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    #[cfg_attr(not(target_arch = "wasm32"), test)]
+    fn timer_clock_drops_a_long_pause() {
+        let mut clock = TimerClock::default();
+
+        assert_eq!(clock.advance(5000.0), MAX_TIMER_FRAMES);
+        assert_eq!(clock.advance(10.0), 0);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn load_game_rejects_a_rom_too_large_for_memory() {
+        // LD V0, 0; LD F, V0; DRW V0, V0, 5 draws the "0" in the top left corner
+        let mut emu = headless();
+        emu.load_game(Uint8Array::from(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05][..])).unwrap();
+
+        // The failed load leaves the first ROM in memory
+        assert!(emu.load_game(Uint8Array::from(&[0x12; 0xE01][..])).is_err());
+        for _ in 0..3 {
+            emu.tick().unwrap();
+        }
+        assert!(emu.chip8.get_display()[0]);
+
+        assert!(emu.load_game(Uint8Array::from(&[0x12; 0xE00][..])).is_ok());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn key_mapping_overrides_the_default_layout() {
+        let mut emu = headless();
+        emu.set_key_mapping("ArrowUp", 0x5).unwrap();
+        emu.set_key_mapping("q", 0xA).unwrap();
+
+        emu.key_changed("ArrowUp", true);
+        emu.key_changed("q", true);
+        assert!(emu.chip8.is_key_pressed(0x5));
+        assert!(emu.chip8.is_key_pressed(0xA));
+        assert!(!emu.chip8.is_key_pressed(0x4));
+
+        emu.key_changed("ArrowUp", false);
+        assert!(!emu.chip8.is_key_pressed(0x5));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn key_mapping_rejects_buttons_past_0xf() {
+        let mut emu = headless();
+
+        assert!(emu.set_key_mapping("ArrowUp", 0x10).is_err());
+        assert!(emu.set_key_mapping("ArrowUp", 0xFF).is_err());
+        emu.key_changed("ArrowUp", true);
+        assert!((0..16).all(|btn| !emu.chip8.is_key_pressed(btn)));
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn load_state_restores_the_saved_state() {
+        // LD V0, 0; LD F, V0; DRW V0, V0, 5 draws the "0" in the top left corner
+        let mut emu = headless();
+        emu.load_game(Uint8Array::from(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05][..])).unwrap();
+        let saved = emu.save_state();
+
+        for _ in 0..3 {
+            emu.tick().unwrap();
+        }
+        let packed = emu.get_display_packed();
+        assert_eq!(packed.len(), 64 * 32 / 8);
+        // A row is 8 bytes, the leftmost pixel is the most significant bit
+        assert_eq!(&packed[..9], &[0xF0, 0, 0, 0, 0, 0, 0, 0, 0x90]);
+        assert_eq!(emu.get_display()[..5], [1, 1, 1, 1, 0]);
+
+        emu.load_state(saved.clone()).unwrap();
+        assert_eq!(emu.save_state().to_vec(), saved.to_vec());
+        assert!(emu.get_display_packed().iter().all(|&byte| byte == 0));
+
+        assert!(emu.load_state(Uint8Array::from(&[1, 2, 3][..])).is_err());
+        assert_eq!(emu.save_state().to_vec(), saved.to_vec());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn beeps_while_the_sound_timer_runs() {
+        // LD V0, 2; LD ST, V0
+        let mut emu = headless();
+        emu.load_game(Uint8Array::from(&[0x60, 0x02, 0xF0, 0x18][..])).unwrap();
+        assert!(!emu.is_beeping());
+
+        emu.tick().unwrap();
+        emu.tick().unwrap();
+        assert!(emu.is_beeping());
+        assert_eq!(emu.get_sound_timer(), 2);
+
+        emu.tick_timers();
+        assert!(emu.is_beeping());
+        emu.tick_timers();
+        assert!(!emu.is_beeping());
+        assert_eq!(emu.get_sound_timer(), 0);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn set_colors_keeps_the_old_color_on_bad_input() {
+        let mut emu = headless();
+        assert_eq!((emu.fg.as_str(), emu.bg.as_str()), (DEFAULT_FG, DEFAULT_BG));
+
+        emu.set_colors(" #ffb000 ", "navy");
+        assert_eq!((emu.fg.as_str(), emu.bg.as_str()), ("#ffb000", "navy"));
+
+        emu.set_colors("", "red; background: url(x)");
+        assert_eq!((emu.fg.as_str(), emu.bg.as_str()), ("#ffb000", "navy"));
+
+        // Without a canvas there is nothing to draw on
+        emu.draw_screen(10);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn step_runs_one_instruction_while_paused() {
+        // LD V0, 1; LD V1, 2; LD V2, 3; LD V3, 4; then an unknown opcode
+        let mut emu = headless();
+        emu.load_game(Uint8Array::from(&[0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0x63, 0x04, 0xFF, 0xFF][..])).unwrap();
+        assert!(!emu.is_paused());
+
+        emu.pause();
+        assert!(emu.is_paused());
+        emu.step().unwrap();
+        assert_eq!(emu.chip8.pc(), 0x202);
+        assert!(emu.is_paused());
+
+        emu.resume();
+        assert!(!emu.is_paused());
+        emu.tick_many(3).unwrap();
+        assert_eq!(&emu.chip8.registers()[..4], &[1, 2, 3, 4]);
+
+        // The error is thrown to JS
+        assert!(emu.tick_many(2).is_err());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn run_frame_reports_whether_the_screen_changed() {
+        // LD V0, 0; LD F, V0; DRW V0, V0, 5; JP 0x206
+        let mut emu = headless();
+        emu.load_game(Uint8Array::from(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06][..])).unwrap();
+
+        // A new emulator starts dirty, so the first frame is drawn
+        assert!(emu.run_frame(2).unwrap());
+        assert!(emu.run_frame(2).unwrap());
+        assert!(!emu.run_frame(2).unwrap());
+        assert!(!emu.take_screen_dirty());
+        assert_eq!(emu.chip8.frame_count(), 3);
+    }
+}
@@ -4,6 +4,9 @@ const WIDTH = 64;
 const HEIGHT = 32;
 const SCALE = 12;
 const TICKS_PER_FRAME = 5;
+// Any CSS color, e.g. "#ffb000" for an amber screen
+const FG_COLOR = "white";
+const BG_COLOR = "black";
 let anim_frame = 0;
 
 const ROMS = [
@@ -37,13 +40,16 @@ canvas.width = WIDTH * SCALE;
 canvas.height = HEIGHT * SCALE;
 
 const ctx = canvas.getContext("2d");
-ctx.fillStyle = "black";
+ctx.fillStyle = BG_COLOR;
 ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE);
 
 const roms = document.getElementById("roms");
 const startButton = document.getElementById("start");
 const stopButton = document.getElementById("stop");
+const stepButton = document.getElementById("step");
 const restartButton = document.getElementById("restart");
+const saveButton = document.getElementById("save");
+const loadButton = document.getElementById("load");
 
 ROMS.forEach(rom => {
     let option = document.createElement("option");
@@ -52,11 +58,65 @@ ROMS.forEach(rom => {
     roms.appendChild(option);
 });
 
+// localStorage only holds strings, so save states are stored as base64
+function bytesToBase64(bytes) {
+    let binary = "";
+    for (let i = 0; i < bytes.length; i++) {
+        binary += String.fromCharCode(bytes[i]);
+    }
+    return btoa(binary);
+}
+
+function base64ToBytes(text) {
+    const binary = atob(text);
+    const bytes = new Uint8Array(binary.length);
+    for (let i = 0; i < binary.length; i++) {
+        bytes[i] = binary.charCodeAt(i);
+    }
+    return bytes;
+}
+
+// The beep is a single square wave oscillator that runs all the time,
+// it's made audible by switching its gain between 0 and the beep volume
+let audioCtx = null;
+let beepGain = null;
+const BEEP_VOLUME = 0.25;
+
+// Browsers only allow audio after a user gesture, so this is called from the input handlers
+function initAudio() {
+    if (audioCtx !== null) {
+        return;
+    }
+
+    audioCtx = new AudioContext();
+    beepGain = audioCtx.createGain();
+    beepGain.gain.value = 0;
+    beepGain.connect(audioCtx.destination);
+
+    const oscillator = audioCtx.createOscillator();
+    oscillator.type = "square";
+    oscillator.frequency.value = 440;
+    oscillator.connect(beepGain);
+    oscillator.start();
+}
+
+function updateBeep(beeping) {
+    if (beepGain !== null) {
+        beepGain.gain.value = beeping ? BEEP_VOLUME : 0;
+    }
+}
+
 async function run() {
     await init();
 
     let chip8 = new wasm.EmuWasm();
+    chip8.set_colors(FG_COLOR, BG_COLOR);
     let currentRom;
+    let currentRomName = ROMS[0];
+    // Set whenever the screen may have changed outside of run_frame, e.g. after a step
+    let needsRedraw = true;
+    // requestAnimationFrame timestamp of the previous frame, null right after a (re)start
+    let lastFrameTime = null;
 
     function loadRom(rom) {
         fetch(`roms/${rom}`)
@@ -73,7 +133,11 @@ async function run() {
                 currentRom = rom;
 
                 chip8.reset();
+                lastFrameTime = null;
+                // Throws if the ROM doesn't fit in memory, the catch below reports it
                 chip8.load_game(rom);
+                chip8.resume();
+                needsRedraw = true;
                 mainloop(chip8);
             })
             .catch(error => {
@@ -86,6 +150,7 @@ async function run() {
     loadRom(ROMS[[0]]);
 
     document.addEventListener("keydown", (evt) => {
+        initAudio();
         chip8.keypress(evt, true);
     });
 
@@ -93,47 +158,104 @@ async function run() {
         chip8.keypress(evt, false);
     });
 
+    // The main loop keeps drawing while paused, so single steps show up on the canvas
     startButton.addEventListener("click", () => {
-        anim_frame = window.requestAnimationFrame(() => mainloop(chip8));
+        initAudio();
+        chip8.resume();
     });
 
     stopButton.addEventListener("click", () => {
-        window.cancelAnimationFrame(anim_frame);
+        chip8.pause();
+    });
+
+    // Runs a single instruction, pausing first if the game is running
+    stepButton.addEventListener("click", () => {
+        chip8.pause();
+        try {
+            chip8.step();
+        } catch (error) {
+            console.error(`Step failed: ${error}`);
+        }
+        needsRedraw = true;
     });
 
     restartButton.addEventListener("click", () => {
         window.cancelAnimationFrame(anim_frame)
         chip8.reset();
-        chip8.load_game(currentRom);
+        lastFrameTime = null;
+        try {
+            chip8.load_game(currentRom);
+        } catch (error) {
+            console.error(`Failed to load ROM: ${error}`);
+            return;
+        }
+        chip8.resume();
+        needsRedraw = true;
         mainloop(chip8);
     })
 
+    // Round trip: save_state() returns an opaque Uint8Array, and load_state() accepts
+    // the same bytes back. load_state() throws on a corrupt or outdated save,
+    // and the running game is left untouched in that case.
+    saveButton.addEventListener("click", () => {
+        const state = chip8.save_state();
+        localStorage.setItem(`chip8-save-${currentRomName}`, bytesToBase64(state));
+    });
+
+    loadButton.addEventListener("click", () => {
+        const saved = localStorage.getItem(`chip8-save-${currentRomName}`);
+        if (saved === null) {
+            console.warn(`No save state for ${currentRomName}`);
+            return;
+        }
+
+        try {
+            chip8.load_state(base64ToBytes(saved));
+            needsRedraw = true;
+        } catch (error) {
+            console.error(`Failed to load save state: ${error}`);
+        }
+    });
+
     roms.addEventListener("change", (evt) => {
         // Stop previous game from rendering, if one exists if (anim_frame != 0) {
         window.cancelAnimationFrame(anim_frame);
 
         let selectedValue = evt.target.value;
+        currentRomName = selectedValue;
 
         loadRom(selectedValue);
     }, false);
 
-    function mainloop(chip8) {
-        // Only draw every few ticks
-        for (let i = 0; i<  TICKS_PER_FRAME; i++) {
-            chip8.tick();
-        }
-        chip8.tick_timers();
+    function mainloop(chip8, timestamp) {
+        // The display may refresh faster than 60Hz, so the timers tick for the time that
+        // actually passed, and the CPU runs a frame of instructions per timer tick
+        const delta = lastFrameTime === null ? 0 : timestamp - lastFrameTime;
+        lastFrameTime = timestamp ?? null;
 
-        // Clear the canvas before drawing
-        ctx.fillStyle = "black";
-        ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE);
+        if (!chip8.is_paused()) {
+            try {
+                const frames = chip8.tick_timers_for(delta);
+                chip8.tick_many(TICKS_PER_FRAME * frames);
+                if (chip8.take_screen_dirty()) {
+                    needsRedraw = true;
+                }
+            } catch (error) {
+                // Pause on a bad ROM instead of spinning on the error, Start resumes
+                console.error(`Emulation paused: ${error}`);
+                chip8.pause();
+            }
+        }
+        updateBeep(!chip8.is_paused() && chip8.is_beeping());
 
-        // Set the draw color back to white before we render our frame;
-        ctx.fillStyle = "white";
-        chip8.draw_screen(SCALE);
+        // Clears to the background color and draws the frame in the foreground color
+        if (needsRedraw) {
+            chip8.draw_screen(SCALE);
+            needsRedraw = false;
+        }
 
-        anim_frame = window.requestAnimationFrame(() => {
-           mainloop(chip8);
+        anim_frame = window.requestAnimationFrame((timestamp) => {
+           mainloop(chip8, timestamp);
         });
     }
 }